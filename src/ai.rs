@@ -0,0 +1,452 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::Ball;
+use crate::game::{GameState, Phase};
+use crate::input::{Inputs, PaddleInput};
+use crate::player::Side;
+use crate::source::{InputSource, TickContext};
+
+/// Change in the ball's velocity, as a fraction of its speed, that counts as
+/// a new heading. Keeps a ball curving under spin from resetting the
+/// reaction timer every tick.
+const HEADING_TOLERANCE: f64 = 0.1;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Perfect,
+}
+
+impl Difficulty {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "easy" => Some(Difficulty::Easy),
+            "normal" => Some(Difficulty::Normal),
+            "hard" => Some(Difficulty::Hard),
+            "perfect" => Some(Difficulty::Perfect),
+            _ => None,
+        }
+    }
+
+    fn profile(self) -> Profile {
+        match self {
+            Difficulty::Easy => Profile {
+                reaction_time: 0.45,
+                aim_error: 60.0,
+                max_bounces: Some(0),
+                max_speed: 0.55,
+            },
+            Difficulty::Normal => Profile {
+                reaction_time: 0.25,
+                aim_error: 30.0,
+                max_bounces: Some(1),
+                max_speed: 0.75,
+            },
+            Difficulty::Hard => Profile {
+                reaction_time: 0.12,
+                aim_error: 10.0,
+                max_bounces: None,
+                max_speed: 0.95,
+            },
+            Difficulty::Perfect => Profile {
+                reaction_time: 0.0,
+                aim_error: 0.0,
+                max_bounces: None,
+                max_speed: 1.0,
+            },
+        }
+    }
+}
+
+struct Profile {
+    /// Seconds between looking at the ball and acting on what it saw.
+    reaction_time: f64,
+    /// Largest random offset, in pixels, added to the predicted intercept.
+    aim_error: f64,
+    /// Wall bounces the prediction follows; `None` follows all of them.
+    max_bounces: Option<u32>,
+    /// Fraction of the paddle's speed the AI will use.
+    max_speed: f64,
+}
+
+/// Drives one paddle by predicting where the ball will cross it.
+pub struct AiController {
+    player: usize,
+    profile: Profile,
+    rng: ChaCha8Rng,
+    /// Where the paddle's centre is headed, along its side. `None` until the
+    /// AI first looks at the ball, which leaves it in the middle.
+    target: Option<f64>,
+    aim_offset: f64,
+    time_to_react: f64,
+    /// Direction of the ball when the target was last picked, so a paddle
+    /// hit or wall bounce makes the AI look again.
+    last_heading: Option<[f64; 2]>,
+}
+
+impl AiController {
+    pub fn new(player: usize, difficulty: Difficulty, seed: u64) -> Self {
+        AiController {
+            player,
+            profile: difficulty.profile(),
+            rng: ChaCha8Rng::seed_from_u64(seed ^ player as u64),
+            target: None,
+            aim_offset: 0.0,
+            time_to_react: 0.0,
+            last_heading: None,
+        }
+    }
+
+    /// Decides the paddle input for the next tick of `dt` seconds.
+    pub fn decide(&mut self, game: &GameState, dt: f64) -> PaddleInput {
+        let heading = self.tracked_ball(game).motion(1.0);
+
+        if self
+            .last_heading
+            .is_none_or(|last| changed_heading(last, heading))
+        {
+            self.last_heading = Some(heading);
+            self.time_to_react = self.profile.reaction_time;
+
+            let error = self.profile.aim_error;
+            self.aim_offset = if error > 0.0 {
+                self.rng.random_range(-error..=error)
+            } else {
+                0.0
+            };
+        }
+
+        let mut reacted = false;
+        self.time_to_react -= dt;
+        if self.time_to_react <= 0.0 {
+            self.time_to_react = self.profile.reaction_time.max(dt);
+            self.target = Some(self.pick_target(game));
+            reacted = true;
+        }
+
+        let paddle = game.player(self.player);
+        let target = self.target.unwrap_or(paddle.side.extent() / 2.0);
+        let max_speed = self.profile.max_speed;
+        let axis = paddle
+            .steer_towards(target, dt)
+            .clamp(-max_speed, max_speed);
+
+        // Serves as soon as it has had time to take in the field.
+        let serve = reacted && game.playing_phase() == Phase::Serve && game.kick_off == self.player;
+
+        PaddleInput { axis, serve }
+    }
+
+    /// The ball to play: whichever reaches this paddle first, or the first
+    /// ball if none is coming.
+    fn tracked_ball<'a>(&self, game: &'a GameState) -> &'a Ball {
+        let paddle = game.player(self.player);
+        let [nx, ny] = paddle.side.normal();
+
+        game.balls
+            .iter()
+            .filter_map(|ball| {
+                let [dx, dy] = ball.motion(1.0);
+                let approach = -(dx * nx + dy * ny);
+                let distance = (ball.position.x - paddle.position.x) * nx
+                    + (ball.position.y - paddle.position.y) * ny;
+                // Coming towards the paddle means moving against its normal.
+                (approach > 0.0).then(|| (ball, distance / approach))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(&game.balls[0], |(ball, _)| ball)
+    }
+
+    fn pick_target(&self, game: &GameState) -> f64 {
+        let ball = self.tracked_ball(game);
+        let paddle = game.player(self.player);
+
+        let (_, front) = paddle.side.along_across(&paddle.in_front(ball.radius()));
+
+        // With four players the ends of a side are other players' goals
+        // until they are out.
+        let walls = game.walls();
+        let ends = paddle.side.ends().map(|end| walls.contains(&end));
+
+        let target = if game.playing_phase() != Phase::Rally {
+            Some(paddle.side.along_across(&ball.position).0)
+        } else {
+            predict_along(ball, paddle.side, front, ends, self.profile.max_bounces)
+        };
+
+        match target {
+            Some(along) => along + self.aim_offset,
+            // Ball is heading away: drift back to the middle.
+            None => paddle.side.extent() / 2.0,
+        }
+    }
+}
+
+/// Where along `side` the ball will be when it reaches `target` across the
+/// field, following at most `max_bounces` bounces off the ends of the side
+/// that `ends` marks as walls (low end first), or `None` if it is moving
+/// away.
+fn predict_along(
+    ball: &Ball,
+    side: Side,
+    target: f64,
+    ends: [bool; 2],
+    max_bounces: Option<u32>,
+) -> Option<f64> {
+    let [dx, dy] = ball.motion(1.0);
+    let (d_along, d_across) = if side.is_vertical() {
+        (dy, dx)
+    } else {
+        (dx, dy)
+    };
+    let (along, across) = side.along_across(&ball.position);
+    let distance = target - across;
+
+    if d_across == 0.0 || distance.signum() != d_across.signum() {
+        return None;
+    }
+
+    let time = distance / d_across;
+    let unfolded = along + d_along * time;
+
+    // Unfold the walls: every span crossed is one bounce.
+    let top = ball.radius();
+    let span = side.extent() - 2.0 * ball.radius();
+    if span <= 0.0 {
+        return Some(side.extent() / 2.0);
+    }
+
+    let offset = unfolded - top;
+    let crossings = (offset / span).floor();
+    let count = crossings.abs() as u32;
+
+    // Unfolded position of the `k`th end crossed, counting from 1. Even
+    // multiples of the span are the low end, odd ones the high end.
+    let boundary = |k: u32| {
+        if crossings > 0.0 {
+            k as f64
+        } else {
+            1.0 - k as f64
+        }
+    };
+    let is_wall = |boundary: f64| ends[boundary.rem_euclid(2.0) as usize];
+
+    // The ball leaves through an open end within two crossings, and the AI
+    // doesn't see past the bounces it can follow. Either way it aims where
+    // the ball will be at that end.
+    let followed = max_bounces.map_or(count, |max| max.min(count));
+    let open = (1..=count.min(2)).find(|&k| !is_wall(boundary(k)));
+    let stop = [open, (count > followed).then_some(followed + 1)]
+        .into_iter()
+        .flatten()
+        .min();
+
+    match stop {
+        Some(k) => Some(fold(top + boundary(k) * span, top, span)),
+        None => Some(fold(unfolded, top, span)),
+    }
+}
+
+impl InputSource for AiController {
+    fn update(&mut self, context: &TickContext, inputs: &mut Inputs) {
+        // Stay frozen along with a paused game, but play along with a step.
+        // A step from the other machine of a networked match arrives too
+        // late to see here, so the paddle sits that tick out.
+        *inputs.paddle_mut(self.player) = if context.game.advances(inputs) {
+            self.decide(context.game, context.dt)
+        } else {
+            PaddleInput::default()
+        };
+    }
+}
+
+/// Whether the ball's velocity went from `from` to `to` by more than a
+/// gradual curve: a bounce, a hit or a change of speed.
+fn changed_heading(from: [f64; 2], to: [f64; 2]) -> bool {
+    let change = (to[0] - from[0]).hypot(to[1] - from[1]);
+    let speed = from[0].hypot(from[1]).max(to[0].hypot(to[1]));
+
+    change > speed * HEADING_TOLERANCE
+}
+
+/// Maps an unfolded position back into the field by mirroring at the walls.
+fn fold(along: f64, top: f64, span: f64) -> f64 {
+    let offset = (along - top).rem_euclid(2.0 * span);
+    if offset <= span {
+        top + offset
+    } else {
+        top + 2.0 * span - offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+    use crate::config::Config;
+    use crate::constants::MAX_PLAYERS;
+    use crate::gamepad::Gamepads;
+    use crate::input::KeyBindings;
+    use crate::stat::Stat;
+    use crate::testing::assert_close;
+    use std::collections::HashSet;
+
+    const BOTH_WALLS: [bool; 2] = [true, true];
+
+    /// A ball of radius 10 at (`x`, `y`) moving by (`dx`, `dy`) per second.
+    fn ball(x: f64, y: f64, dx: f64, dy: f64) -> Ball {
+        let speed = dx.hypot(dy);
+        Ball {
+            radius: Stat::new(10.0, 1.0, 100.0),
+            speed: Stat::new(speed, 0.0, speed * 2.0),
+            angle: (-dy).atan2(dx),
+            spin: 0.0,
+            position: Position { x, y },
+            last_hit: 1,
+        }
+    }
+
+    fn predict(ball: &Ball, ends: [bool; 2], max_bounces: Option<u32>) -> Option<f64> {
+        predict_along(ball, Side::Right, 700.0, ends, max_bounces)
+    }
+
+    #[test]
+    fn predicts_a_straight_path() {
+        let along = predict(&ball(400.0, 400.0, 1.0, 0.5), BOTH_WALLS, Some(0));
+        assert_close(along.unwrap(), 550.0);
+    }
+
+    #[test]
+    fn predicts_a_ball_moving_away_as_none() {
+        assert!(predict(&ball(400.0, 400.0, -1.0, 0.5), BOTH_WALLS, None).is_none());
+    }
+
+    #[test]
+    fn predicts_one_bounce() {
+        // Off the bottom wall, whose centre line is at 790: 90 down, 60 up.
+        let along = predict(&ball(400.0, 700.0, 1.0, 0.5), BOTH_WALLS, Some(1));
+        assert_close(along.unwrap(), 730.0);
+
+        // Off the top wall, at 10: 90 up, 60 down.
+        let along = predict(&ball(400.0, 100.0, 1.0, -0.5), BOTH_WALLS, Some(1));
+        assert_close(along.unwrap(), 70.0);
+    }
+
+    #[test]
+    fn predicts_two_bounces() {
+        // 390 down to the bottom, 780 up to the top and 30 back down.
+        let along = predict(&ball(100.0, 400.0, 1.0, 2.0), BOTH_WALLS, None);
+        assert_close(along.unwrap(), 40.0);
+    }
+
+    #[test]
+    fn stops_at_the_first_bounce_past_max_bounces() {
+        let two_bounces = ball(100.0, 400.0, 1.0, 2.0);
+        assert_close(predict(&two_bounces, BOTH_WALLS, Some(1)).unwrap(), 10.0);
+        assert_close(predict(&two_bounces, BOTH_WALLS, Some(0)).unwrap(), 790.0);
+    }
+
+    #[test]
+    fn stops_at_an_open_end() {
+        let one_bounce = ball(400.0, 700.0, 1.0, 0.5);
+        assert_close(predict(&one_bounce, [true, false], None).unwrap(), 790.0);
+        assert_close(predict(&one_bounce, [false, true], None).unwrap(), 730.0);
+
+        let two_bounces = ball(100.0, 400.0, 1.0, 2.0);
+        assert_close(predict(&two_bounces, [false, true], None).unwrap(), 10.0);
+    }
+
+    #[test]
+    fn a_curve_is_not_a_new_heading_but_a_bounce_is() {
+        assert!(!changed_heading([100.0, 0.0], [99.0, 5.0]));
+        assert!(changed_heading([100.0, 0.0], [-100.0, 0.0]));
+        assert!(changed_heading([100.0, 10.0], [100.0, -10.0]));
+    }
+
+    /// Ticks before an AI of `difficulty` first picks a target.
+    fn ticks_to_react(difficulty: Difficulty) -> u32 {
+        let mut config = Config::default();
+        config.load_power_ups().unwrap();
+        let dt = 1.0 / config.tick_rate;
+        let mut game = GameState::new(1, config);
+        game.start();
+
+        let mut ai = AiController::new(2, difficulty, 1);
+        let mut ticks = 0;
+        while ai.target.is_none() {
+            ai.decide(&game, dt);
+            game.step(dt, &Inputs::default());
+            ticks += 1;
+        }
+        ticks
+    }
+
+    #[test]
+    fn a_paused_ai_moves_only_on_a_step() {
+        let mut config = Config::default();
+        config.load_power_ups().unwrap();
+        let dt = 1.0 / config.tick_rate;
+        let keys = HashSet::new();
+        let bindings = KeyBindings::default();
+        let gamepads = Gamepads::new(config.gamepad.clone(), [false; MAX_PLAYERS]);
+
+        let mut game = GameState::new(1, config);
+        game.start();
+        let mut serve = Inputs::default();
+        serve.paddles[0].serve = true;
+        game.step(dt, &serve);
+        // Off to one side, so the paddle has to move to meet it.
+        game.balls[0].angle = -0.5;
+        let pause = Inputs {
+            pause: true,
+            ..Inputs::default()
+        };
+        game.step(dt, &pause);
+        game.step(dt, &Inputs::default());
+        assert_eq!(game.phase, Phase::Paused);
+
+        let mut ai = AiController::new(2, Difficulty::Perfect, 1);
+        let mut tick = |game: &mut GameState, step: bool| {
+            let context = TickContext {
+                game,
+                dt,
+                keys: &keys,
+                bindings: &bindings,
+                gamepads: &gamepads,
+            };
+            let mut inputs = Inputs {
+                step,
+                ..Inputs::default()
+            };
+            ai.update(&context, &mut inputs);
+            game.step(dt, &inputs);
+            inputs.paddles[1]
+        };
+
+        let before = game.balls[0].position;
+        assert!(tick(&mut game, false) == PaddleInput::default());
+        assert_eq!(game.balls[0].position.x, before.x);
+
+        let stepped = tick(&mut game, true);
+        assert!(stepped.axis > 0.0);
+        assert_eq!(game.phase, Phase::Paused);
+        let [dx, dy] = game.balls[0].motion(dt);
+        assert_close(game.balls[0].position.x, before.x + dx);
+        assert_close(game.balls[0].position.y, before.y + dy);
+
+        // Holding step doesn't step again.
+        let after = game.balls[0].position;
+        assert!(tick(&mut game, true) == PaddleInput::default());
+        assert_eq!(game.balls[0].position.x, after.x);
+    }
+
+    #[test]
+    fn reaction_time_follows_the_difficulty() {
+        assert_eq!(ticks_to_react(Difficulty::Perfect), 1);
+        assert_eq!(ticks_to_react(Difficulty::Hard), 15);
+        assert_eq!(ticks_to_react(Difficulty::Easy), 54);
+    }
+}
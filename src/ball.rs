@@ -1,29 +1,177 @@
-use crate::HEIGHT;
+use std::f64::consts::PI;
+
 use crate::Position;
+use crate::config::SpinConfig;
+use crate::constants::{HEIGHT, WIDTH};
 use crate::object::Object;
+use crate::player::Side;
+use crate::stat::Stat;
 
+/// The ball bounced off a wall.
+#[derive(Clone, Copy)]
+pub struct WallBounce {
+    pub wall: Side,
+    /// Ball centre at the moment of the bounce.
+    pub position: Position,
+}
+
+#[derive(Clone)]
 pub struct Ball {
-    pub radius: f64,
-    pub speed: f64,
+    pub radius: Stat,
+    /// Base speed is set by serves and paddle hits; effects change it on
+    /// top of that.
+    pub speed: Stat,
     pub angle: f64,
+    /// How fast the direction of travel turns, in radians per second;
+    /// positive turns anticlockwise on screen.
+    pub spin: f64,
     pub position: Position,
     pub last_hit: usize,
 }
 
 impl Ball {
-    pub fn check_and_handle_vertical_collision(&mut self) {
-        if self.position.y - self.radius < 0.0 || self.position.y + self.radius > HEIGHT {
-            self.angle = -self.angle;
+    /// Effective radius, with effects applied.
+    pub fn radius(&self) -> f64 {
+        self.radius.value()
+    }
+
+    /// Effective speed, with effects applied. A ball waiting to be served
+    /// has a base speed of zero and stays put whatever its effects.
+    pub fn speed(&self) -> f64 {
+        if self.speed.base == 0.0 {
+            0.0
+        } else {
+            self.speed.value()
         }
     }
+
+    /// Distance covered in `dt` seconds.
+    pub fn motion(&self, dt: f64) -> [f64; 2] {
+        [
+            self.angle.cos() * self.speed() * dt,
+            -self.angle.sin() * self.speed() * dt,
+        ]
+    }
+
+    /// Mirrors the direction of travel on a surface with `normal`, unless the
+    /// ball is already moving away from it. Returns whether it bounced.
+    pub fn reflect(&mut self, normal: [f64; 2]) -> bool {
+        let velocity = [self.angle.cos(), -self.angle.sin()];
+        let along_normal = velocity[0] * normal[0] + velocity[1] * normal[1];
+
+        if along_normal >= 0.0 {
+            return false;
+        }
+
+        let reflected = [
+            velocity[0] - 2.0 * along_normal * normal[0],
+            velocity[1] - 2.0 * along_normal * normal[1],
+        ];
+        self.angle = (-reflected[1]).atan2(reflected[0]);
+        true
+    }
+
+    /// Bends the path by the spin over `dt` seconds and lets the spin wear
+    /// off. Given a `max_angle`, the ball keeps its horizontal direction and
+    /// never gets steeper than that.
+    pub fn curve(&mut self, dt: f64, spin: &SpinConfig, max_angle: Option<f64>) {
+        let rightwards = self.angle.cos() >= 0.0;
+        self.angle += self.spin * dt;
+        if let Some(max_angle) = max_angle {
+            self.angle = limit_elevation(self.angle, rightwards, max_angle);
+        }
+        self.spin *= (-spin.decay * dt).exp();
+    }
+
+    /// Lets the spin grip a wall the ball just bounced off: the rebound
+    /// is kicked round in the spin's direction, and the spin reverses as the
+    /// path is mirrored. The kick never sends the ball back into the wall.
+    pub fn spin_off_wall(&mut self, spin: &SpinConfig, wall: Side, max_angle: Option<f64>) {
+        let rightwards = self.angle.cos() >= 0.0;
+        let normal = wall.normal();
+        let away = |angle: f64| angle.cos() * normal[0] - angle.sin() * normal[1];
+
+        let mut angle = self.angle + self.spin * spin.wall_grip;
+        if away(angle) <= 0.0 {
+            // Run along the wall instead, in the direction already going.
+            angle = if wall.is_vertical() {
+                if self.angle.sin() > 0.0 {
+                    PI / 2.0
+                } else {
+                    -PI / 2.0
+                }
+            } else if rightwards {
+                0.0
+            } else {
+                PI
+            };
+        }
+
+        self.angle = match max_angle {
+            Some(max_angle) => limit_elevation(angle, rightwards, max_angle),
+            None => angle,
+        };
+        self.spin = -self.spin;
+    }
+
+    pub fn advance(&mut self, dt: f64) {
+        let [dx, dy] = self.motion(dt);
+        self.position.x += dx;
+        self.position.y += dy;
+    }
+
+    /// Keeps the ball inside `walls`. A ball overlapping a wall is moved back
+    /// out, and only bounces if it is heading into it, so it can't get stuck
+    /// flipping direction inside the wall.
+    pub fn bounce_off_walls(&mut self, walls: &[Side]) -> Option<WallBounce> {
+        let radius = self.radius();
+
+        for &wall in walls {
+            let overlap = match wall {
+                Side::Left => radius - self.position.x,
+                Side::Right => self.position.x + radius - WIDTH,
+                Side::Top => radius - self.position.y,
+                Side::Bottom => self.position.y + radius - HEIGHT,
+            };
+            if overlap <= 0.0 {
+                continue;
+            }
+
+            let normal = wall.normal();
+            self.position.x += normal[0] * overlap;
+            self.position.y += normal[1] * overlap;
+            if self.reflect(normal) {
+                return Some(WallBounce {
+                    wall,
+                    position: self.position,
+                });
+            }
+        }
+
+        None
+    }
+}
+
+/// `angle` turned back to at most `max_angle` from horizontal, heading
+/// right or left as given.
+fn limit_elevation(angle: f64, rightwards: bool, max_angle: f64) -> f64 {
+    let direction = [angle.cos(), angle.sin()];
+    let elevation = direction[1].atan2(direction[0].abs());
+    let elevation = elevation.clamp(-max_angle, max_angle);
+
+    if rightwards {
+        elevation
+    } else {
+        PI - elevation
+    }
 }
 
 impl Object for Ball {
     fn resize_by(&mut self, size: f64) {
-        self.radius += size;
+        self.radius.modify(size);
     }
 
     fn change_speed_by(&mut self, speed: f64) {
-        self.speed += speed;
+        self.speed.modify(speed);
     }
 }
@@ -0,0 +1,282 @@
+use crate::Position;
+
+/// Axis-aligned rectangle, `x`/`y` being its top-left corner.
+#[derive(Clone, Copy)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Result of a swept test.
+#[derive(Clone, Copy)]
+pub struct Hit {
+    /// Fraction of the motion travelled before contact, in `0.0..=1.0`.
+    pub time: f64,
+    /// Unit normal of the touched surface, pointing towards the circle.
+    pub normal: [f64; 2],
+}
+
+/// Overlap between a circle and a rectangle.
+#[derive(Clone, Copy)]
+pub struct Contact {
+    /// Unit normal of the touched face or corner, pointing towards the circle.
+    pub normal: [f64; 2],
+    /// How far the circle has to move along `normal` to stop overlapping.
+    pub depth: f64,
+}
+
+/// Tests a circle of `radius` at `center` against `rect`.
+///
+/// A circle touching a face gets that face's normal; one touching a corner
+/// gets the direction from the corner to its centre.
+pub fn circle_rect_contact(center: &Position, radius: f64, rect: &Rect) -> Option<Contact> {
+    debug_assert!(rect.width >= 0.0 && rect.height >= 0.0);
+    let closest = [
+        center.x.clamp(rect.x, rect.x + rect.width),
+        center.y.clamp(rect.y, rect.y + rect.height),
+    ];
+    let offset = [center.x - closest[0], center.y - closest[1]];
+    let distance = offset[0].hypot(offset[1]);
+
+    if distance >= radius {
+        return None;
+    }
+
+    if distance > 0.0 {
+        return Some(Contact {
+            normal: [offset[0] / distance, offset[1] / distance],
+            depth: radius - distance,
+        });
+    }
+
+    // The centre is inside the rectangle: push it out through the nearest face.
+    let faces = [
+        (center.x - rect.x, [-1.0, 0.0]),
+        (rect.x + rect.width - center.x, [1.0, 0.0]),
+        (center.y - rect.y, [0.0, -1.0]),
+        (rect.y + rect.height - center.y, [0.0, 1.0]),
+    ];
+    let (gap, normal) = faces
+        .into_iter()
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .unwrap();
+
+    Some(Contact {
+        normal,
+        depth: gap + radius,
+    })
+}
+
+/// Sweeps a circle of `radius` from `center` along `motion` against `rect`.
+///
+/// Returns the first contact along the path. A circle that already overlaps
+/// the rectangle at the start of the motion is not reported.
+pub fn sweep_circle_rect(
+    center: &Position,
+    motion: [f64; 2],
+    radius: f64,
+    rect: &Rect,
+) -> Option<Hit> {
+    // Sweeping a circle against a rectangle is the same as casting a ray
+    // against the rectangle grown by the radius with rounded corners.
+    let min = [rect.x - radius, rect.y - radius];
+    let max = [rect.x + rect.width + radius, rect.y + rect.height + radius];
+    let origin = [center.x, center.y];
+
+    let mut t_enter = f64::NEG_INFINITY;
+    let mut t_exit = f64::INFINITY;
+    let mut normal = [0.0, 0.0];
+
+    for axis in 0..2 {
+        if motion[axis] == 0.0 {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let t1 = (min[axis] - origin[axis]) / motion[axis];
+        let t2 = (max[axis] - origin[axis]) / motion[axis];
+        let (near, far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+
+        if near > t_enter {
+            t_enter = near;
+            normal = [0.0, 0.0];
+            normal[axis] = -motion[axis].signum();
+        }
+        t_exit = t_exit.min(far);
+    }
+
+    if t_enter > t_exit || t_enter > 1.0 || t_exit < 0.0 {
+        return None;
+    }
+
+    if t_enter < 0.0 {
+        // Starting inside the grown rectangle is only a miss so far if the
+        // circle sits in one of its rounded-off corners.
+        return match corner_region(rect, origin) {
+            Some(corner) if distance(origin, corner) > radius => {
+                sweep_circle_point(origin, motion, radius, corner)
+            }
+            _ => None,
+        };
+    }
+
+    let contact = [
+        origin[0] + motion[0] * t_enter,
+        origin[1] + motion[1] * t_enter,
+    ];
+
+    match corner_region(rect, contact) {
+        Some(corner) => sweep_circle_point(origin, motion, radius, corner),
+        None => Some(Hit {
+            time: t_enter,
+            normal,
+        }),
+    }
+}
+
+/// The corner of `rect` nearest to `point` if `point` lies diagonally
+/// outside it, past both of the corner's edges.
+fn corner_region(rect: &Rect, point: [f64; 2]) -> Option<[f64; 2]> {
+    let x = if point[0] < rect.x {
+        rect.x
+    } else if point[0] > rect.x + rect.width {
+        rect.x + rect.width
+    } else {
+        return None;
+    };
+    let y = if point[1] < rect.y {
+        rect.y
+    } else if point[1] > rect.y + rect.height {
+        rect.y + rect.height
+    } else {
+        return None;
+    };
+
+    Some([x, y])
+}
+
+fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    (a[0] - b[0]).hypot(a[1] - b[1])
+}
+
+/// Ray cast against the circle of `radius` around `point`.
+fn sweep_circle_point(
+    origin: [f64; 2],
+    motion: [f64; 2],
+    radius: f64,
+    point: [f64; 2],
+) -> Option<Hit> {
+    let offset = [origin[0] - point[0], origin[1] - point[1]];
+
+    let a = motion[0] * motion[0] + motion[1] * motion[1];
+    let b = 2.0 * (offset[0] * motion[0] + offset[1] * motion[1]);
+    let c = offset[0] * offset[0] + offset[1] * offset[1] - radius * radius;
+
+    let discriminant = b * b - 4.0 * a * c;
+    if a == 0.0 || discriminant < 0.0 {
+        return None;
+    }
+
+    let time = (-b - discriminant.sqrt()) / (2.0 * a);
+    if !(0.0..=1.0).contains(&time) {
+        return None;
+    }
+
+    let normal = [
+        (offset[0] + motion[0] * time) / radius,
+        (offset[1] + motion[1] * time) / radius,
+    ];
+
+    Some(Hit { time, normal })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::assert_close;
+
+    const PADDLE: Rect = Rect {
+        x: 100.0,
+        y: 100.0,
+        width: 10.0,
+        height: 50.0,
+    };
+
+    fn at(x: f64, y: f64) -> Position {
+        Position { x, y }
+    }
+
+    #[test]
+    fn sweep_hits_a_face() {
+        let hit = sweep_circle_rect(&at(50.0, 125.0), [100.0, 0.0], 5.0, &PADDLE).unwrap();
+        assert_close(hit.time, 0.45);
+        assert_eq!(hit.normal, [-1.0, 0.0]);
+
+        let hit = sweep_circle_rect(&at(105.0, 50.0), [0.0, 100.0], 5.0, &PADDLE).unwrap();
+        assert_close(hit.time, 0.45);
+        assert_eq!(hit.normal, [0.0, -1.0]);
+    }
+
+    #[test]
+    fn sweep_hits_a_corner() {
+        let hit = sweep_circle_rect(&at(80.0, 80.0), [40.0, 40.0], 5.0, &PADDLE).unwrap();
+        let offset = 5.0 / 2.0_f64.sqrt();
+        assert_close(hit.time, (100.0 - offset - 80.0) / 40.0);
+        assert_close(hit.normal[0], -1.0 / 2.0_f64.sqrt());
+        assert_close(hit.normal[1], -1.0 / 2.0_f64.sqrt());
+    }
+
+    #[test]
+    fn sweep_misses_past_a_corner() {
+        // Crosses the grown rectangle's corner but stays clear of the
+        // rounded edge around the real one.
+        assert!(sweep_circle_rect(&at(86.0, 106.0), [20.0, -20.0], 5.0, &PADDLE).is_none());
+    }
+
+    #[test]
+    fn sweep_misses() {
+        assert!(sweep_circle_rect(&at(50.0, 50.0), [100.0, 0.0], 5.0, &PADDLE).is_none());
+        // Moving away.
+        assert!(sweep_circle_rect(&at(50.0, 125.0), [-100.0, 0.0], 5.0, &PADDLE).is_none());
+        // Falls short of the paddle this tick.
+        assert!(sweep_circle_rect(&at(50.0, 125.0), [40.0, 0.0], 5.0, &PADDLE).is_none());
+    }
+
+    #[test]
+    fn sweep_ignores_a_starting_overlap() {
+        assert!(sweep_circle_rect(&at(102.0, 125.0), [100.0, 0.0], 5.0, &PADDLE).is_none());
+        assert!(sweep_circle_rect(&at(97.0, 125.0), [100.0, 0.0], 5.0, &PADDLE).is_none());
+    }
+
+    #[test]
+    fn contact_with_a_face() {
+        let contact = circle_rect_contact(&at(97.0, 125.0), 5.0, &PADDLE).unwrap();
+        assert_eq!(contact.normal, [-1.0, 0.0]);
+        assert_close(contact.depth, 2.0);
+
+        assert!(circle_rect_contact(&at(95.0, 125.0), 5.0, &PADDLE).is_none());
+    }
+
+    #[test]
+    fn contact_with_a_corner_points_away_from_it() {
+        let contact = circle_rect_contact(&at(97.0, 96.0), 6.0, &PADDLE).unwrap();
+        assert_close(contact.normal[0], -0.6);
+        assert_close(contact.normal[1], -0.8);
+        assert_close(contact.depth, 1.0);
+    }
+
+    #[test]
+    fn contact_pushes_a_centre_inside_out_through_the_nearest_face() {
+        let contact = circle_rect_contact(&at(102.0, 125.0), 5.0, &PADDLE).unwrap();
+        assert_eq!(contact.normal, [-1.0, 0.0]);
+        assert_close(contact.depth, 7.0);
+
+        let contact = circle_rect_contact(&at(105.0, 147.0), 5.0, &PADDLE).unwrap();
+        assert_eq!(contact.normal, [0.0, 1.0]);
+        assert_close(contact.depth, 8.0);
+    }
+}
@@ -0,0 +1,787 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::constants::{HEIGHT, TICK_RATE, WIDTH};
+use crate::effect::Stacking;
+use crate::gamepad::GamepadConfig;
+use crate::input::KeyBindings;
+use crate::mouse::MouseConfig;
+use crate::powerup::PowerUpDef;
+use crate::rules::MatchRules;
+
+/// Config file picked up from the working directory when no `--config` is given.
+pub const DEFAULT_CONFIG_PATH: &str = "pong.ron";
+
+/// Power-up definitions used unless the config names another file.
+pub const DEFAULT_POWERUPS_PATH: &str = "assets/powerups.ron";
+
+/// Gameplay tuning, loaded from a RON file. Every field is optional in the
+/// file and falls back to its default.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Simulation ticks per second.
+    pub tick_rate: f64,
+    /// Seed for the match RNG; picked at random when not given.
+    pub seed: Option<u64>,
+    pub paddle: PaddleConfig,
+    pub ball: BallConfig,
+    pub powerups: PowerUpConfig,
+    pub rules: MatchRules,
+    pub keys: KeyBindings,
+    pub gamepad: GamepadConfig,
+    pub mouse: MouseConfig,
+}
+
+/// Sizes in pixels, speeds in pixels per second.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaddleConfig {
+    pub width: f64,
+    pub height: f64,
+    /// Top speed.
+    pub speed: f64,
+    /// How quickly the paddle picks up speed, in pixels per second squared.
+    pub acceleration: f64,
+    /// How quickly it slows down when let go or turned round.
+    pub deceleration: f64,
+    /// Gap between a paddle and its side of the window.
+    pub inset: f64,
+    /// Limits power-ups can't push the height and speed past.
+    pub min_height: f64,
+    pub max_height: f64,
+    pub min_speed: f64,
+    pub max_speed: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BallConfig {
+    pub radius: f64,
+    /// Speed after a serve or a hit on the middle of a paddle.
+    pub serve_speed: f64,
+    /// Extra speed for a hit on the very end of a paddle.
+    pub edge_hit_bonus: f64,
+    /// Steepest return angle off a paddle, in degrees.
+    pub max_bounce_angle: f64,
+    /// Steepest angle a serve can be aimed at, in degrees.
+    pub max_serve_angle: f64,
+    /// Seconds before the ball is served on its own.
+    pub auto_serve_time: f64,
+    /// Limits power-ups and paddle hits can't push the radius and speed
+    /// past.
+    pub min_radius: f64,
+    pub max_radius: f64,
+    pub min_speed: f64,
+    pub max_speed: f64,
+    pub spin: SpinConfig,
+}
+
+/// Spin a moving paddle puts on the ball.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpinConfig {
+    pub enabled: bool,
+    /// Spin in radians per second for each pixel per second the paddle
+    /// was moving at when it hit the ball.
+    pub transfer: f64,
+    /// Rate at which spin wears off, per second.
+    pub decay: f64,
+    /// Seconds of spin turned into a kick when the ball hits a wall.
+    pub wall_grip: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerUpConfig {
+    /// Seconds between power-up spawns.
+    pub spawn_interval: f64,
+    /// What collecting a power-up that is already in effect does.
+    pub stacking: Stacking,
+    /// RON file with the list of power-up definitions.
+    pub definitions_file: PathBuf,
+    /// The power-ups that can spawn. Read from `definitions_file` unless
+    /// listed here; replays store them so they play back the same.
+    pub definitions: Vec<PowerUpDef>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            tick_rate: TICK_RATE,
+            seed: None,
+            paddle: PaddleConfig::default(),
+            ball: BallConfig::default(),
+            powerups: PowerUpConfig::default(),
+            rules: MatchRules::default(),
+            keys: KeyBindings::default(),
+            gamepad: GamepadConfig::default(),
+            mouse: MouseConfig::default(),
+        }
+    }
+}
+
+impl Default for PaddleConfig {
+    fn default() -> Self {
+        PaddleConfig {
+            width: 16.0,
+            height: 80.0,
+            speed: 600.0,
+            acceleration: 6000.0,
+            deceleration: 9000.0,
+            inset: 50.0,
+            min_height: 20.0,
+            max_height: 300.0,
+            min_speed: 150.0,
+            max_speed: 1500.0,
+        }
+    }
+}
+
+impl Default for BallConfig {
+    fn default() -> Self {
+        BallConfig {
+            radius: 10.0,
+            serve_speed: 360.0,
+            edge_hit_bonus: 360.0,
+            max_bounce_angle: 75.0,
+            max_serve_angle: 45.0,
+            auto_serve_time: 3.0,
+            min_radius: 4.0,
+            max_radius: 40.0,
+            min_speed: 120.0,
+            max_speed: 1500.0,
+            spin: SpinConfig::default(),
+        }
+    }
+}
+
+impl Default for SpinConfig {
+    fn default() -> Self {
+        SpinConfig {
+            enabled: true,
+            transfer: 0.002,
+            decay: 1.0,
+            wall_grip: 0.15,
+        }
+    }
+}
+
+impl Default for PowerUpConfig {
+    fn default() -> Self {
+        PowerUpConfig {
+            spawn_interval: 10.0,
+            stacking: Stacking::Refresh,
+            definitions_file: PathBuf::from(DEFAULT_POWERUPS_PATH),
+            definitions: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, ron::error::SpannedError),
+    Invalid(String),
+    Serialize(ron::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => write!(f, "{}: {err}", path.display()),
+            ConfigError::Parse(path, err) => write!(f, "{}: {err}", path.display()),
+            ConfigError::Invalid(message) => write!(f, "invalid config: {message}"),
+            ConfigError::Serialize(err) => write!(f, "could not write config: {err}"),
+        }
+    }
+}
+
+impl Config {
+    /// Loads and validates the config at `path`.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let mut config: Config = read_ron(path)?;
+
+        config.load_power_ups()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Loads `path`, or `pong.ron` if it exists, or the defaults.
+    pub fn load_or_default(path: Option<&Path>) -> Result<Self, ConfigError> {
+        match path {
+            Some(path) => Self::load(path),
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::load(Path::new(DEFAULT_CONFIG_PATH))
+            }
+            None => {
+                let mut config = Config::default();
+                config.load_power_ups()?;
+                Ok(config)
+            }
+        }
+    }
+
+    /// Reads the power-up definitions file, unless the config already
+    /// lists the definitions itself.
+    pub(crate) fn load_power_ups(&mut self) -> Result<(), ConfigError> {
+        if self.powerups.definitions.is_empty() {
+            self.powerups.definitions = read_ron(&self.powerups.definitions_file)?;
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let paddle = &self.paddle;
+        let ball = &self.ball;
+        let powerups = &self.powerups;
+
+        positive("tick_rate", self.tick_rate)?;
+
+        positive("paddle.width", paddle.width)?;
+        positive("paddle.height", paddle.height)?;
+        positive("paddle.speed", paddle.speed)?;
+        positive("paddle.acceleration", paddle.acceleration)?;
+        positive("paddle.deceleration", paddle.deceleration)?;
+        if paddle.max_height >= HEIGHT {
+            return invalid(format!("paddle.max_height must be less than {HEIGHT}"));
+        }
+        within_limits(
+            "paddle.height",
+            paddle.height,
+            paddle.min_height,
+            paddle.max_height,
+        )?;
+        within_limits(
+            "paddle.speed",
+            paddle.speed,
+            paddle.min_speed,
+            paddle.max_speed,
+        )?;
+        if !(0.0..WIDTH / 2.0 - paddle.width).contains(&paddle.inset) {
+            return invalid(format!(
+                "paddle.inset must be between 0 and {}",
+                WIDTH / 2.0 - paddle.width
+            ));
+        }
+
+        positive("ball.radius", ball.radius)?;
+        positive("ball.serve_speed", ball.serve_speed)?;
+        if ball.max_radius >= HEIGHT / 2.0 {
+            return invalid(format!(
+                "ball.max_radius must be less than {}",
+                HEIGHT / 2.0
+            ));
+        }
+        within_limits("ball.radius", ball.radius, ball.min_radius, ball.max_radius)?;
+        within_limits(
+            "ball.serve_speed",
+            ball.serve_speed,
+            ball.min_speed,
+            ball.max_speed,
+        )?;
+        if ball.edge_hit_bonus.is_nan() || ball.edge_hit_bonus < 0.0 {
+            return invalid("ball.edge_hit_bonus must not be negative".to_string());
+        }
+        if !(ball.max_bounce_angle > 0.0 && ball.max_bounce_angle < 90.0) {
+            return invalid("ball.max_bounce_angle must be between 0 and 90 degrees".to_string());
+        }
+        if !(0.0..90.0).contains(&ball.max_serve_angle) {
+            return invalid("ball.max_serve_angle must be between 0 and 90 degrees".to_string());
+        }
+        positive("ball.auto_serve_time", ball.auto_serve_time)?;
+        for (name, value) in [
+            ("ball.spin.transfer", ball.spin.transfer),
+            ("ball.spin.decay", ball.spin.decay),
+            ("ball.spin.wall_grip", ball.spin.wall_grip),
+        ] {
+            if !(value >= 0.0 && value.is_finite()) {
+                return invalid(format!("{name} must not be negative"));
+            }
+        }
+
+        positive("powerups.spawn_interval", powerups.spawn_interval)?;
+        if powerups.stacking == Stacking::Cap(0) {
+            return invalid("powerups.stacking cap must be at least 1".to_string());
+        }
+        for def in &powerups.definitions {
+            def.validate()
+                .map_err(|message| ConfigError::Invalid(format!("powerups: {message}")))?;
+        }
+        if !powerups.definitions.iter().any(|def| def.weight > 0.0) {
+            return invalid("powerups: at least one power-up needs a weight above 0".to_string());
+        }
+
+        if !(0.0..1.0).contains(&self.gamepad.dead_zone) {
+            return invalid("gamepad.dead_zone must be at least 0 and less than 1".to_string());
+        }
+        positive("mouse.sensitivity", self.mouse.sensitivity)?;
+
+        self.keys
+            .validate()
+            .map_err(|message| ConfigError::Invalid(format!("keys: {message}")))?;
+
+        self.rules
+            .validate()
+            .map_err(|message| ConfigError::Invalid(format!("rules: {message}")))
+    }
+
+    /// Writes `keys` into the config file at `path`, creating it if needed.
+    /// Only the `keys` section changes; the rest of the file, comments
+    /// included, is left as it was, so settings left out keep following the
+    /// defaults.
+    pub fn save_keys(path: &Path, keys: &KeyBindings) -> Result<(), ConfigError> {
+        let text = if path.exists() {
+            // Only rewrite a file that reads back as a config.
+            let _: Config = read_ron(path)?;
+            fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?
+        } else {
+            "(\n)\n".to_string()
+        };
+
+        let pretty = ron::ser::PrettyConfig::new();
+        let section = ron::ser::to_string_pretty(keys, pretty).map_err(ConfigError::Serialize)?;
+        let text = set_field(&text, "keys", &section).ok_or_else(|| {
+            ConfigError::Invalid(format!("{}: no settings to add keys to", path.display()))
+        })?;
+
+        fs::write(path, text).map_err(|err| ConfigError::Io(path.to_path_buf(), err))
+    }
+}
+
+/// Sets the top-level field `name` of the RON struct in `text` to `value`,
+/// replacing the field's old value or adding it at the end. Everything
+/// else, comments and layout included, is kept. `None` if `text` holds no
+/// struct.
+fn set_field(text: &str, name: &str, value: &str) -> Option<String> {
+    let value = value.replace('\n', "\n    ");
+    let chars = significant_chars(text);
+    let open = chars.iter().position(|&(_, c)| c == '(')?;
+
+    let mut depth = 0;
+    let mut in_field = false;
+    let mut starts_value = false;
+    let mut span = None;
+    let mut start = 0;
+    // The last character seen, and the byte just past it.
+    let mut last = '(';
+    let mut end = 0;
+
+    for &(i, c) in &chars[open..] {
+        if starts_value {
+            start = i;
+            starts_value = false;
+        }
+
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+
+        if depth == 0 {
+            if in_field {
+                span = Some((start, end));
+            }
+            let text = match span {
+                Some((start, end)) => format!("{}{value}{}", &text[..start], &text[end..]),
+                None => {
+                    let comma = if matches!(last, ',' | '(') { "" } else { "," };
+                    let newline = if text[end..i].contains('\n') {
+                        ""
+                    } else {
+                        "\n"
+                    };
+                    format!(
+                        "{}{comma}\n    {name}: {value},{newline}{}",
+                        &text[..end],
+                        &text[end..]
+                    )
+                }
+            };
+            return Some(text);
+        }
+
+        if depth == 1 {
+            match c {
+                ',' if in_field => {
+                    span = Some((start, end));
+                    in_field = false;
+                }
+                ':' if span.is_none() => {
+                    let before = text[..i].trim_end();
+                    if before.ends_with(name)
+                        && !before[..before.len() - name.len()]
+                            .ends_with(|c: char| c.is_alphanumeric() || c == '_')
+                    {
+                        in_field = true;
+                        starts_value = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        last = c;
+        end = i + c.len_utf8();
+    }
+
+    None
+}
+
+/// Byte offsets of the characters in `text` that aren't whitespace or
+/// comments. Strings only show up as their quotes.
+fn significant_chars(text: &str) -> Vec<(usize, char)> {
+    let mut found = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, next)| next);
+        match c {
+            '/' if next == Some('/') => {
+                for (_, c) in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if next == Some('*') => {
+                chars.next();
+                let mut previous = ' ';
+                for (_, c) in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            '"' => {
+                found.push((i, c));
+                let mut escaped = false;
+                for (j, c) in chars.by_ref() {
+                    if escaped {
+                        escaped = false;
+                    } else if c == '\\' {
+                        escaped = true;
+                    } else if c == '"' {
+                        found.push((j, c));
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => found.push((i, c)),
+        }
+    }
+
+    found
+}
+
+fn read_ron<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, ConfigError> {
+    let text = fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+    ron::from_str(&text).map_err(|err| ConfigError::Parse(path.to_path_buf(), err))
+}
+
+fn positive(name: &str, value: f64) -> Result<(), ConfigError> {
+    if value > 0.0 && value.is_finite() {
+        Ok(())
+    } else {
+        invalid(format!("{name} must be a positive number, got {value}"))
+    }
+}
+
+/// Checks that a min/max pair is usable and that `value` is inside it.
+fn within_limits(name: &str, value: f64, min: f64, max: f64) -> Result<(), ConfigError> {
+    positive(&format!("{name} minimum"), min)?;
+    positive(&format!("{name} maximum"), max)?;
+    if min > max {
+        return invalid(format!("{name} minimum {min} is above its maximum {max}"));
+    }
+    if !(min..=max).contains(&value) {
+        return invalid(format!("{name} {value} must be between {min} and {max}"));
+    }
+    Ok(())
+}
+
+fn invalid(message: String) -> Result<(), ConfigError> {
+    Err(ConfigError::Invalid(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{Action, MENU_KEY};
+    use piston::Key;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.load_power_ups().unwrap();
+        config
+    }
+
+    /// Checks that `change` makes the default config invalid, with an error
+    /// mentioning `expected`.
+    #[track_caller]
+    fn rejects(change: impl FnOnce(&mut Config), expected: &str) {
+        let mut config = config();
+        change(&mut config);
+        match config.validate() {
+            Err(ConfigError::Invalid(message)) => {
+                assert!(message.contains(expected), "{message}")
+            }
+            Err(err) => panic!("unexpected error: {err}"),
+            Ok(()) => panic!("accepted a config that should fail on {expected}"),
+        }
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        config().validate().unwrap();
+    }
+
+    #[test]
+    fn rejects_a_tick_rate_that_is_not_positive() {
+        rejects(|config| config.tick_rate = 0.0, "tick_rate");
+        rejects(|config| config.tick_rate = f64::NAN, "tick_rate");
+    }
+
+    #[test]
+    fn rejects_paddle_sizes_and_speeds_that_are_not_positive() {
+        rejects(|config| config.paddle.width = 0.0, "paddle.width");
+        rejects(|config| config.paddle.height = -1.0, "paddle.height");
+        rejects(|config| config.paddle.speed = 0.0, "paddle.speed");
+        rejects(
+            |config| config.paddle.acceleration = 0.0,
+            "paddle.acceleration",
+        );
+        rejects(
+            |config| config.paddle.deceleration = 0.0,
+            "paddle.deceleration",
+        );
+    }
+
+    #[test]
+    fn rejects_a_paddle_taller_than_the_field() {
+        rejects(
+            |config| config.paddle.max_height = HEIGHT,
+            "paddle.max_height",
+        );
+    }
+
+    #[test]
+    fn rejects_stats_outside_their_limits() {
+        rejects(|config| config.paddle.height = 1000.0, "paddle.height 1000");
+        rejects(
+            |config| config.paddle.min_speed = 0.0,
+            "paddle.speed minimum",
+        );
+        rejects(
+            |config| config.paddle.min_speed = config.paddle.max_speed + 1.0,
+            "is above its maximum",
+        );
+        rejects(|config| config.ball.radius = 1000.0, "ball.radius 1000");
+        rejects(
+            |config| config.ball.serve_speed = 10000.0,
+            "ball.serve_speed",
+        );
+    }
+
+    #[test]
+    fn rejects_a_paddle_inset_off_the_field() {
+        rejects(|config| config.paddle.inset = -1.0, "paddle.inset");
+        rejects(|config| config.paddle.inset = WIDTH / 2.0, "paddle.inset");
+    }
+
+    #[test]
+    fn rejects_a_ball_that_is_not_positive() {
+        rejects(|config| config.ball.radius = 0.0, "ball.radius");
+        rejects(|config| config.ball.serve_speed = 0.0, "ball.serve_speed");
+    }
+
+    #[test]
+    fn rejects_a_ball_taller_than_half_the_field() {
+        rejects(
+            |config| config.ball.max_radius = HEIGHT / 2.0,
+            "ball.max_radius",
+        );
+    }
+
+    #[test]
+    fn rejects_a_negative_edge_hit_bonus() {
+        rejects(|config| config.ball.edge_hit_bonus = -0.1, "edge_hit_bonus");
+    }
+
+    #[test]
+    fn rejects_angles_out_of_range() {
+        rejects(
+            |config| config.ball.max_bounce_angle = 0.0,
+            "max_bounce_angle",
+        );
+        rejects(
+            |config| config.ball.max_bounce_angle = 90.0,
+            "max_bounce_angle",
+        );
+        rejects(
+            |config| config.ball.max_serve_angle = -1.0,
+            "max_serve_angle",
+        );
+        rejects(
+            |config| config.ball.max_serve_angle = 90.0,
+            "max_serve_angle",
+        );
+    }
+
+    #[test]
+    fn rejects_an_auto_serve_time_that_is_not_positive() {
+        rejects(
+            |config| config.ball.auto_serve_time = 0.0,
+            "auto_serve_time",
+        );
+    }
+
+    #[test]
+    fn rejects_negative_spin() {
+        rejects(
+            |config| config.ball.spin.transfer = -1.0,
+            "ball.spin.transfer",
+        );
+        rejects(|config| config.ball.spin.decay = -1.0, "ball.spin.decay");
+        rejects(
+            |config| config.ball.spin.wall_grip = f64::NAN,
+            "ball.spin.wall_grip",
+        );
+    }
+
+    #[test]
+    fn rejects_a_spawn_interval_that_is_not_positive() {
+        rejects(
+            |config| config.powerups.spawn_interval = 0.0,
+            "spawn_interval",
+        );
+    }
+
+    #[test]
+    fn rejects_a_stacking_cap_of_zero() {
+        rejects(
+            |config| config.powerups.stacking = Stacking::Cap(0),
+            "stacking cap",
+        );
+    }
+
+    #[test]
+    fn rejects_an_invalid_power_up() {
+        rejects(
+            |config| config.powerups.definitions[0].duration = 0.0,
+            "duration must be positive",
+        );
+    }
+
+    #[test]
+    fn rejects_power_ups_that_can_never_spawn() {
+        rejects(
+            |config| {
+                for def in &mut config.powerups.definitions {
+                    def.weight = 0.0;
+                }
+            },
+            "weight above 0",
+        );
+        rejects(
+            |config| config.powerups.definitions.clear(),
+            "weight above 0",
+        );
+    }
+
+    #[test]
+    fn rejects_a_dead_zone_out_of_range() {
+        rejects(|config| config.gamepad.dead_zone = 1.0, "gamepad.dead_zone");
+        rejects(
+            |config| config.gamepad.dead_zone = -0.1,
+            "gamepad.dead_zone",
+        );
+    }
+
+    #[test]
+    fn rejects_a_mouse_sensitivity_that_is_not_positive() {
+        rejects(|config| config.mouse.sensitivity = 0.0, "mouse.sensitivity");
+    }
+
+    #[test]
+    fn rejects_invalid_keys() {
+        rejects(
+            |config| {
+                config.keys.bind(Action::Pause, MENU_KEY);
+            },
+            "keys:",
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        rejects(|config| config.rules.target_score = 0, "rules:");
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("pong-{}-{name}.ron", std::process::id()))
+    }
+
+    /// Saves keys with P1 Up on E into a file holding `text`, if any, and
+    /// returns what the file ends up holding.
+    fn save_keys_into(name: &str, text: Option<&str>) -> (String, Config) {
+        let path = temp_path(name);
+        if let Some(text) = text {
+            fs::write(&path, text).unwrap();
+        }
+        let mut keys = KeyBindings::default();
+        keys.bind(Action::P1Up, Key::E);
+
+        Config::save_keys(&path, &keys).unwrap();
+        let saved = fs::read_to_string(&path).unwrap();
+        let config = Config::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let config = config.unwrap();
+        assert_eq!(config.keys.key(Action::P1Up), Key::E);
+        (saved, config)
+    }
+
+    #[test]
+    fn save_keys_only_replaces_the_keys() {
+        let (saved, config) = save_keys_into(
+            "replace",
+            Some(
+                "// My settings\n(\n    tick_rate: 240.0, // smoother\n    keys: {\n        P1Up: Q,\n    },\n    paddle: (speed: 700.0),\n)\n",
+            ),
+        );
+
+        assert!(
+            saved
+                .starts_with("// My settings\n(\n    tick_rate: 240.0, // smoother\n    keys: {\n")
+        );
+        assert!(saved.ends_with("    },\n    paddle: (speed: 700.0),\n)\n"));
+        assert_eq!(config.tick_rate, 240.0);
+        assert_eq!(config.paddle.speed, 700.0);
+    }
+
+    #[test]
+    fn save_keys_adds_keys_to_a_file_without_them() {
+        let (saved, config) =
+            save_keys_into("add", Some("(\n    // Fast.\n    tick_rate: 240.0\n)\n"));
+
+        assert!(saved.starts_with("(\n    // Fast.\n    tick_rate: 240.0,\n    keys: {\n"));
+        assert!(saved.ends_with("    },\n)\n"));
+        assert_eq!(config.tick_rate, 240.0);
+    }
+
+    #[test]
+    fn save_keys_creates_a_file_with_only_the_keys() {
+        let (saved, config) = save_keys_into("create", None);
+
+        assert!(saved.starts_with("(\n    keys: {\n"));
+        assert!(!saved.contains("tick_rate"));
+        assert_eq!(config.tick_rate, TICK_RATE);
+    }
+}
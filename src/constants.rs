@@ -1,3 +1,9 @@
 pub const WIDTH: f64 = 800f64;
 pub const HEIGHT: f64 = 800f64;
 pub const SPRITE_SPAWN_MARGIN: f64 = 150.0;
+
+/// Simulation ticks per second.
+pub const TICK_RATE: f64 = 120.0;
+
+/// Most players a match can have, one on each side of the field.
+pub const MAX_PLAYERS: usize = 4;
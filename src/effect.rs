@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+use crate::object::Object;
+
+/// What a power-up changes.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum EffectKind {
+    /// Paddle height or ball radius.
+    Resize,
+    Speed,
+    /// Splits the ball that collected it into `magnitude` more balls.
+    /// Happens at once, so it has no duration.
+    Split,
+}
+
+/// Who a power-up affects, relative to the player who collected it.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Target {
+    Collector,
+    Opponent,
+    Ball,
+}
+
+/// The object an effect was applied to. Anything that implements `Object`
+/// can be one; `GameState::affected_mut` hands out the object itself.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Affected {
+    Player(usize),
+    Ball,
+}
+
+/// A change a collected power-up makes to a paddle or the ball.
+#[derive(Clone, Copy, Debug)]
+pub struct Effect {
+    pub kind: EffectKind,
+    pub target: Affected,
+    pub amount: f64,
+}
+
+impl Effect {
+    pub fn apply(&self, object: &mut dyn Object) {
+        self.change(object, self.amount);
+    }
+
+    /// Undoes `apply`.
+    pub fn revert(&self, object: &mut dyn Object) {
+        self.change(object, -self.amount);
+    }
+
+    fn change(&self, object: &mut dyn Object, amount: f64) {
+        match self.kind {
+            EffectKind::Resize => object.resize_by(amount),
+            EffectKind::Speed => object.change_speed_by(amount),
+            // Handled by the game when collected; never put in force.
+            EffectKind::Split => {}
+        }
+    }
+}
+
+/// What collecting a power-up does while the same power-up is still in
+/// effect on the same target.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Stacking {
+    /// Restart the running effect's timer without adding to it.
+    Refresh,
+    /// Add another effect with its own timer.
+    Stack,
+    /// Stack up to this many, then refresh the one closest to running out.
+    Cap(u32),
+}
+
+/// An effect in force, counting down to when it is reverted.
+pub struct ActiveEffect {
+    /// Index of the power-up definition it came from.
+    pub source: usize,
+    pub effect: Effect,
+    pub duration: f64,
+    pub remaining: f64,
+}
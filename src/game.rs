@@ -0,0 +1,1190 @@
+use rand::distr::Distribution;
+use rand::distr::weighted::WeightedIndex;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::Ball;
+use crate::Player;
+use crate::Position;
+use crate::ball::WallBounce;
+use crate::collision::Contact;
+use crate::config::Config;
+use crate::constants::*;
+use crate::effect::{ActiveEffect, Affected, Effect, EffectKind, Stacking};
+use crate::input::Inputs;
+use crate::object::Object;
+use crate::player::Side;
+use crate::powerup::PowerUp;
+use crate::rules::MultiballScoring;
+use crate::stat::Stat;
+
+/// Paddle contacts resolved per tick before the rest of the motion is dropped.
+const MAX_CONTACTS_PER_TICK: usize = 4;
+
+/// Gap left between ball and paddle after a contact so the next sweep
+/// doesn't start out touching it.
+const CONTACT_SEPARATION: f64 = 1e-6;
+
+/// Seconds between a point being scored and the next serve.
+const POINT_SCORED_DELAY: f64 = 1.0;
+
+/// Seconds of movement that swing the serve aim from straight ahead to its
+/// steepest angle.
+const SERVE_AIM_TIME: f64 = 0.5;
+
+/// Gap between the server's paddle and the ball waiting on it.
+const SERVE_GAP: f64 = 10.0;
+
+/// Most balls in play at once; splits beyond it are dropped.
+const MAX_BALLS: usize = 8;
+
+/// Angle between the balls a split sends out, in radians.
+const SPLIT_SPREAD: f64 = 0.3;
+
+/// Something that happened during a tick, for systems outside the
+/// simulation such as rendering effects.
+pub enum GameEvent {
+    WallBounce(WallBounce),
+    GameOver { winner: usize },
+}
+
+/// Where the match is. Each phase has its own update in `GameState::step`
+/// and its own screen in `render::Scene`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Phase {
+    /// Waiting for start to be pressed.
+    Title,
+    /// The ball sits on the kick-off player's paddle until they launch it
+    /// or the auto-serve time runs out.
+    Serve,
+    Rally,
+    /// Short break after a point before the next serve. `game_won` is set
+    /// when the point also ended a game of the series.
+    PointScored {
+        scorer: usize,
+        game_won: bool,
+    },
+    /// The four-player version of `PointScored`: `player` let a goal in
+    /// and lost a life, and is out if `eliminated` is set.
+    LifeLost {
+        player: usize,
+        eliminated: bool,
+    },
+    GameOver,
+    /// Everything is frozen until pause is pressed again, apart from
+    /// single steps.
+    Paused,
+}
+
+/// All match state, free of any window or graphics handle.
+pub struct GameState {
+    /// One paddle per player, player 1 first.
+    pub players: Vec<Player>,
+    /// Balls in play. There is always at least one; the first is the one
+    /// that gets served.
+    pub balls: Vec<Ball>,
+    pub active_powerups: Vec<PowerUp>,
+    /// Effects of collected power-ups, in the order they were collected.
+    pub effects: Vec<ActiveEffect>,
+    pub kick_off: usize,
+    /// Direction of the next serve, from -1.0 (steepest up or left) to 1.0
+    /// (steepest down or right).
+    pub serve_aim: f64,
+    pub config: Config,
+    pub phase: Phase,
+    /// Phase to go back to when the game is unpaused.
+    resume_phase: Phase,
+    /// Seconds spent in the current phase.
+    phase_time: f64,
+    /// Points in the current game, per player. Unused with four players.
+    pub score: Vec<u32>,
+    /// Games won in the series, per player.
+    pub games_won: Vec<u32>,
+    /// Lives left per player in a four-player match; a player with none is
+    /// out, and their side of the field becomes a wall.
+    pub lives: Vec<u32>,
+    /// Seconds the ball has been in play this game.
+    pub game_time: f64,
+    /// The time limit ran out with the score level; the tiebreak decides.
+    pub overtime: bool,
+    pub winner: usize,
+    /// Quit was pressed on the game over screen.
+    pub quit_requested: bool,
+    time_to_spawn_power_up: f64,
+    rng: ChaCha8Rng,
+    events: Vec<GameEvent>,
+    previous_inputs: Inputs,
+}
+
+impl GameState {
+    /// Creates a new match on the title screen. Two matches with the same
+    /// seed and the same inputs play out identically.
+    pub fn new(seed: u64, config: Config) -> Self {
+        let count = config.rules.players;
+        let players = Side::for_players(count)
+            .iter()
+            .map(|&side| Player::new(side, &config.paddle))
+            .collect();
+
+        let ball = Ball {
+            radius: Stat::new(
+                config.ball.radius,
+                config.ball.min_radius,
+                config.ball.max_radius,
+            ),
+            speed: Stat::new(0.0, config.ball.min_speed, config.ball.max_speed),
+            position: Position { x: 0.0, y: 0.0 },
+            angle: 0.0, //radians
+            spin: 0.0,
+            last_hit: 1,
+        };
+
+        let mut game = GameState {
+            players,
+            balls: vec![ball],
+            active_powerups: Vec::new(),
+            effects: Vec::new(),
+            kick_off: 1,
+            serve_aim: 0.0,
+            phase: Phase::Title,
+            resume_phase: Phase::Title,
+            phase_time: 0.0,
+            score: vec![0; count],
+            games_won: vec![0; count],
+            lives: vec![config.rules.lives; count],
+            game_time: 0.0,
+            overtime: false,
+            winner: 0,
+            quit_requested: false,
+            time_to_spawn_power_up: config.powerups.spawn_interval,
+            config,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            events: Vec::new(),
+            previous_inputs: Inputs::default(),
+        };
+        game.attach_ball_to_server();
+        game
+    }
+
+    /// Leaves the title screen, as if start had been pressed.
+    pub fn start(&mut self) {
+        if self.phase == Phase::Title {
+            self.enter(Phase::Serve);
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.phase == Phase::GameOver
+    }
+
+    /// The phase being played, or the one a pause will resume.
+    pub fn playing_phase(&self) -> Phase {
+        if self.phase == Phase::Paused {
+            self.resume_phase
+        } else {
+            self.phase
+        }
+    }
+
+    /// Whether a tick with `inputs` moves the match on. A paused game only
+    /// moves on when step is newly pressed.
+    ///
+    /// Sources only see the inputs decided before them, so a step pressed on
+    /// the other machine of a networked match still moves the game on
+    /// although local sources asking here were told it wouldn't.
+    pub fn advances(&self, inputs: &Inputs) -> bool {
+        if self.phase != Phase::Paused {
+            return true;
+        }
+        let pressed = inputs.pressed_since(&self.previous_inputs);
+        pressed.step && !pressed.pause
+    }
+
+    /// Advances the simulation by one fixed tick of `dt` seconds.
+    pub fn step(&mut self, dt: f64, inputs: &Inputs) {
+        self.events.clear();
+
+        let pressed = inputs.pressed_since(&self.previous_inputs);
+        self.previous_inputs = *inputs;
+
+        if self.phase == Phase::Paused {
+            self.update_paused(dt, &pressed);
+        } else {
+            self.update_phase(dt, &pressed);
+        }
+    }
+
+    /// Paddle of `player`, counting from 1.
+    pub fn player(&self, player: usize) -> &Player {
+        &self.players[player - 1]
+    }
+
+    /// Whether `player` lost their last life in a four-player match.
+    pub fn is_out(&self, player: usize) -> bool {
+        self.config.rules.is_four_player() && self.lives[player - 1] == 0
+    }
+
+    /// Players still in the match, counting from 1.
+    pub fn players_in(&self) -> impl Iterator<Item = usize> + '_ {
+        (1..=self.players.len()).filter(|&player| !self.is_out(player))
+    }
+
+    /// The next player after `player` still in the match, in player order.
+    /// With two players, simply the other one.
+    pub fn next_player(&self, player: usize) -> usize {
+        let count = self.players.len();
+        (1..count)
+            .map(|step| (player - 1 + step) % count + 1)
+            .find(|&next| !self.is_out(next))
+            .unwrap_or(player)
+    }
+
+    /// Sides the ball bounces off: the top and bottom with two players,
+    /// and the sides of players who are out with four.
+    pub fn walls(&self) -> Vec<Side> {
+        if self.config.rules.is_four_player() {
+            (1..=self.players.len())
+                .filter(|&player| self.is_out(player))
+                .map(|player| self.player(player).side)
+                .collect()
+        } else {
+            vec![Side::Top, Side::Bottom]
+        }
+    }
+
+    /// Steepest angle from straight across the ball may travel at, if any.
+    /// Only two-player matches keep the ball heading from side to side.
+    fn angle_limit(&self) -> Option<f64> {
+        (!self.config.rules.is_four_player())
+            .then(|| self.config.ball.max_bounce_angle.to_radians())
+    }
+
+    /// The paddle or balls an effect changes. Ball effects apply to every
+    /// ball in play, and balls split off later inherit them.
+    fn affected_mut(&mut self, affected: Affected) -> Vec<&mut dyn Object> {
+        match affected {
+            Affected::Player(player) => vec![&mut self.players[player - 1]],
+            Affected::Ball => self
+                .balls
+                .iter_mut()
+                .map(|ball| ball as &mut dyn Object)
+                .collect(),
+        }
+    }
+
+    /// Events raised by the last call to `step`.
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    /// Runs one tick of the current phase.
+    fn update_phase(&mut self, dt: f64, pressed: &Inputs) {
+        self.phase_time += dt;
+
+        match self.phase {
+            Phase::Title => self.update_title(pressed),
+            Phase::Serve => self.update_serve(dt, pressed),
+            Phase::Rally => self.update_rally(dt, pressed),
+            Phase::PointScored { .. } | Phase::LifeLost { .. } => self.update_point_scored(pressed),
+            Phase::GameOver => self.update_game_over(pressed),
+            // `step` hands paused ticks to `update_paused` instead.
+            Phase::Paused => {}
+        }
+    }
+
+    fn enter(&mut self, phase: Phase) {
+        self.phase = phase;
+        self.phase_time = 0.0;
+    }
+
+    /// Pauses the match if pause was pressed; returns whether it did.
+    fn handle_pause(&mut self, pressed: &Inputs) -> bool {
+        if pressed.pause {
+            self.resume_phase = self.phase;
+            self.phase = Phase::Paused;
+        }
+        pressed.pause
+    }
+
+    fn update_title(&mut self, pressed: &Inputs) {
+        if pressed.start {
+            self.start();
+        }
+    }
+
+    fn update_serve(&mut self, dt: f64, inputs: &Inputs) {
+        if self.handle_pause(inputs) {
+            return;
+        }
+
+        self.move_paddles(dt, inputs);
+
+        let server = inputs.paddle(self.kick_off);
+
+        // Moving the paddle swings the aim the same way.
+        self.serve_aim = (self.serve_aim + server.axis * dt / SERVE_AIM_TIME).clamp(-1.0, 1.0);
+        self.attach_ball_to_server();
+
+        if server.serve || self.phase_time >= self.config.ball.auto_serve_time {
+            let ball = &mut self.balls[0];
+            ball.speed.base = self.config.ball.serve_speed;
+            ball.last_hit = self.kick_off;
+            self.enter(Phase::Rally);
+        }
+    }
+
+    /// Puts the ball in front of the kick-off player's paddle, pointing
+    /// along the serve aim.
+    fn attach_ball_to_server(&mut self) {
+        let max_angle = self.config.ball.max_serve_angle.to_radians();
+        let server = self.player(self.kick_off);
+        let position = server.in_front(self.balls[0].radius() + SERVE_GAP);
+        let angle = server.angle_towards(self.serve_aim, max_angle);
+
+        let ball = &mut self.balls[0];
+        ball.position = position;
+        ball.angle = angle;
+    }
+
+    fn update_rally(&mut self, dt: f64, inputs: &Inputs) {
+        if self.handle_pause(inputs) {
+            return;
+        }
+
+        self.move_paddles(dt, inputs);
+
+        let spin = self.config.ball.spin;
+        let max_angle = self.angle_limit();
+        let walls = self.walls();
+        let players: Vec<usize> = self.players_in().collect();
+
+        // Balls split off during the tick start moving on the next one.
+        for i in 0..self.balls.len() {
+            if spin.enabled {
+                self.balls[i].curve(dt, &spin, max_angle);
+            }
+            self.move_ball(i, dt);
+
+            let ball = &mut self.balls[i];
+            if let Some(bounce) = ball.bounce_off_walls(&walls) {
+                if spin.enabled {
+                    ball.spin_off_wall(&spin, bounce.wall, max_angle);
+                }
+                self.events.push(GameEvent::WallBounce(bounce));
+            }
+
+            // The sweep only reports contacts along the ball's own path, so a
+            // paddle moving onto the ball is still caught here.
+            for &player in &players {
+                if let Some(contact) = self.player(player).contact(&self.balls[i]) {
+                    self.resolve_overlap(i, &contact);
+                    self.paddle_hit(i, player, contact.normal);
+                }
+            }
+        }
+
+        if self.handle_goals() {
+            return;
+        }
+
+        self.game_time += dt;
+        if self.time_left() == Some(0.0) {
+            if self.score[0] == self.score[1] {
+                self.overtime = true;
+            } else if !self.overtime {
+                let leader = if self.score[0] > self.score[1] { 1 } else { 2 };
+                self.end_point(leader);
+                return;
+            }
+        }
+
+        self.update_effects(dt);
+
+        self.time_to_spawn_power_up -= dt;
+        if self.time_to_spawn_power_up <= 0.0 {
+            self.spawn_power_up();
+            self.time_to_spawn_power_up = self.config.powerups.spawn_interval;
+        }
+    }
+
+    fn update_point_scored(&mut self, pressed: &Inputs) {
+        if self.handle_pause(pressed) {
+            return;
+        }
+
+        if self.phase_time >= POINT_SCORED_DELAY {
+            self.reset_for_kick_off();
+            self.enter(Phase::Serve);
+        }
+    }
+
+    fn update_game_over(&mut self, pressed: &Inputs) {
+        if pressed.rematch {
+            self.rematch();
+        } else if pressed.quit {
+            self.quit_requested = true;
+        }
+    }
+
+    /// Nothing moves and no timer runs while paused, except for a single
+    /// step, which plays exactly one tick of the paused phase.
+    fn update_paused(&mut self, dt: f64, pressed: &Inputs) {
+        if pressed.pause {
+            self.phase = self.resume_phase;
+        } else if pressed.step {
+            self.phase = self.resume_phase;
+            self.update_phase(dt, pressed);
+
+            // The tick may have moved on to another phase, e.g. after a point.
+            if self.phase != Phase::GameOver {
+                self.resume_phase = self.phase;
+                self.phase = Phase::Paused;
+            }
+        }
+    }
+
+    fn move_paddles(&mut self, dt: f64, inputs: &Inputs) {
+        for (player, input) in self.players.iter_mut().zip(&inputs.paddles) {
+            player.move_by_input(input, dt);
+            player.prevent_out_of_bounds();
+        }
+    }
+
+    /// Moves ball `index` for one tick. The ball stops at the first paddle
+    /// in its path and spends the rest of the tick travelling in its new
+    /// direction, so fast balls can't tunnel through a paddle.
+    fn move_ball(&mut self, index: usize, dt: f64) {
+        let mut remaining = dt;
+
+        for _ in 0..MAX_CONTACTS_PER_TICK {
+            let ball = &self.balls[index];
+            let motion = ball.motion(remaining);
+            let start = ball.position;
+
+            let hit = self
+                .players_in()
+                .filter_map(|player| {
+                    let hit = self.player(player).sweep(ball, motion)?;
+                    Some((player, hit))
+                })
+                .min_by(|a, b| a.1.time.total_cmp(&b.1.time));
+
+            let Some((player, hit)) = hit else {
+                self.balls[index].advance(remaining);
+                self.collect_power_ups(index, &start, motion);
+                return;
+            };
+
+            let ball = &mut self.balls[index];
+            ball.advance(remaining * hit.time);
+            ball.position.x += hit.normal[0] * CONTACT_SEPARATION;
+            ball.position.y += hit.normal[1] * CONTACT_SEPARATION;
+            self.collect_power_ups(index, &start, [motion[0] * hit.time, motion[1] * hit.time]);
+
+            self.paddle_hit(index, player, hit.normal);
+            remaining *= 1.0 - hit.time;
+        }
+    }
+
+    fn resolve_overlap(&mut self, index: usize, contact: &Contact) {
+        let depth = contact.depth + CONTACT_SEPARATION;
+        let ball = &mut self.balls[index];
+        ball.position.x += contact.normal[0] * depth;
+        ball.position.y += contact.normal[1] * depth;
+    }
+
+    /// Deflects ball `index` off a paddle. Front-face hits aim the ball by
+    /// where it met the paddle; edge and corner hits bounce along the
+    /// contact normal.
+    fn paddle_hit(&mut self, index: usize, player: usize, normal: [f64; 2]) {
+        let ball_config = &self.config.ball;
+        let max_angle = ball_config.max_bounce_angle.to_radians();
+        let paddle = &self.players[player - 1];
+        let ball = &mut self.balls[index];
+
+        if !paddle.is_front(normal) {
+            ball.reflect(normal);
+        } else {
+            let collision_point = paddle.collision_point(ball);
+            ball.angle = paddle.angle_towards(collision_point, max_angle);
+            ball.speed.base =
+                ball_config.serve_speed + collision_point.abs() * ball_config.edge_hit_bonus;
+
+            // The ball curves the way the paddle was moving.
+            if ball_config.spin.enabled {
+                ball.spin = paddle.turn() * paddle.velocity * ball_config.spin.transfer;
+            }
+        }
+        ball.last_hit = player;
+    }
+
+    /// Collects every power-up ball `index` passed through moving from
+    /// `from` along `motion`.
+    fn collect_power_ups(&mut self, index: usize, from: &Position, motion: [f64; 2]) {
+        let ball = &self.balls[index];
+        let mut collected_indices = Vec::new();
+
+        for (i, powerup) in self.active_powerups.iter().enumerate() {
+            if powerup.collided(ball) || powerup.swept(ball, from, motion) {
+                collected_indices.push(i);
+            }
+        }
+
+        for i in collected_indices.into_iter().rev() {
+            let powerup = self.active_powerups.remove(i);
+            let def = &self.config.powerups.definitions[powerup.kind];
+            let duration = def.duration;
+            let collector = self.balls[index].last_hit;
+            let effect = powerup.collect(def, collector, self.next_player(collector));
+
+            if effect.kind == EffectKind::Split {
+                self.split_ball(index, effect.amount.round() as usize);
+            } else {
+                self.add_effect(powerup.kind, effect, duration);
+            }
+        }
+    }
+
+    /// Sends `extra` new balls off from ball `index`, fanned out around
+    /// its direction. They share its owner, size and speed.
+    fn split_ball(&mut self, index: usize, extra: usize) {
+        let parent = self.balls[index].clone();
+
+        for k in 0..extra.min(MAX_BALLS - self.balls.len()) {
+            // Alternate sides: +1, -1, +2, -2, ... spreads.
+            let side = if k % 2 == 0 { 1.0 } else { -1.0 };
+            let spread = (k / 2 + 1) as f64 * SPLIT_SPREAD;
+
+            let mut ball = parent.clone();
+            ball.angle += side * spread;
+            self.balls.push(ball);
+        }
+    }
+
+    /// Scores any balls that went past a paddle. Returns whether the rally
+    /// is over.
+    fn handle_goals(&mut self) -> bool {
+        // Highest index first, so removing a ball doesn't shift the rest.
+        let goals: Vec<(usize, usize)> = self
+            .balls
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(|(i, ball)| {
+                let conceder = self
+                    .players_in()
+                    .find(|&player| self.player(player).is_behind(ball))?;
+                Some((i, conceder))
+            })
+            .collect();
+
+        for (i, conceder) in goals {
+            let last_ball = self.balls.len() == 1;
+            let ends_rally =
+                last_ball || self.config.rules.multiball == MultiballScoring::FirstGoal;
+
+            if self.config.rules.is_four_player() {
+                // Losing the last life reshapes the field, so it always ends
+                // the rally.
+                if ends_rally || self.lives[conceder - 1] == 1 {
+                    self.lose_life(conceder);
+                    return true;
+                }
+                self.balls.remove(i);
+                self.lives[conceder - 1] -= 1;
+                self.kick_off = conceder;
+                continue;
+            }
+
+            let scorer = self.next_player(conceder);
+            if ends_rally {
+                self.scored(scorer);
+                return true;
+            }
+
+            // Every goal counts and play goes on with the other balls,
+            // unless this one decided the game.
+            self.balls.remove(i);
+            self.score[scorer - 1] += 1;
+            self.kick_off = conceder;
+
+            let time_up = self.time_left() == Some(0.0);
+            if self
+                .config
+                .rules
+                .game_winner(self.two_player_score(), time_up, self.overtime)
+                .is_some()
+            {
+                self.end_point(scorer);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Puts `effect` in force, following the configured stacking rule if
+    /// the same power-up is already in effect on the same target.
+    fn add_effect(&mut self, source: usize, effect: Effect, duration: f64) {
+        let limit = match self.config.powerups.stacking {
+            Stacking::Refresh => 1,
+            Stacking::Stack => usize::MAX,
+            Stacking::Cap(max) => max as usize,
+        };
+
+        let is_same = |active: &&mut ActiveEffect| {
+            active.source == source && active.effect.target == effect.target
+        };
+
+        if self.effects.iter_mut().filter(is_same).count() >= limit {
+            if let Some(oldest) = self
+                .effects
+                .iter_mut()
+                .filter(is_same)
+                .min_by(|a, b| a.remaining.total_cmp(&b.remaining))
+            {
+                oldest.remaining = duration;
+            }
+            return;
+        }
+
+        for object in self.affected_mut(effect.target) {
+            effect.apply(object);
+        }
+        self.effects.push(ActiveEffect {
+            source,
+            effect,
+            duration,
+            remaining: duration,
+        });
+    }
+
+    /// Counts down the effects in force and reverts the ones that ran out.
+    fn update_effects(&mut self, dt: f64) {
+        for active in &mut self.effects {
+            active.remaining -= dt;
+        }
+
+        let (expired, running): (Vec<_>, Vec<_>) = std::mem::take(&mut self.effects)
+            .into_iter()
+            .partition(|active| active.remaining <= 0.0);
+        self.effects = running;
+
+        for active in expired {
+            let effect = active.effect;
+            for object in self.affected_mut(effect.target) {
+                effect.revert(object);
+            }
+        }
+    }
+
+    /// Reverts every effect in force.
+    fn clear_effects(&mut self) {
+        for active in std::mem::take(&mut self.effects) {
+            let effect = active.effect;
+            for object in self.affected_mut(effect.target) {
+                effect.revert(object);
+            }
+        }
+    }
+
+    /// Seconds left in the current game, if it has a time limit.
+    pub fn time_left(&self) -> Option<f64> {
+        self.config
+            .rules
+            .time_limit
+            .map(|limit| (limit - self.game_time).max(0.0))
+    }
+
+    fn two_player_score(&self) -> [u32; 2] {
+        [self.score[0], self.score[1]]
+    }
+
+    /// Takes a life from `player` after a goal in a four-player match. A
+    /// player who loses their last life is out; the last one left wins.
+    fn lose_life(&mut self, player: usize) {
+        self.lives[player - 1] -= 1;
+        let eliminated = self.lives[player - 1] == 0;
+
+        let remaining: Vec<usize> = self.players_in().collect();
+        if let [winner] = remaining[..] {
+            self.winner = winner;
+            self.events.push(GameEvent::GameOver { winner });
+            self.enter(Phase::GameOver);
+            return;
+        }
+
+        // Whoever let the goal in serves next, or the next player along if
+        // they are out.
+        self.kick_off = if eliminated {
+            self.next_player(player)
+        } else {
+            player
+        };
+        self.enter(Phase::LifeLost { player, eliminated });
+    }
+
+    fn scored(&mut self, scoring_player: usize) {
+        self.score[scoring_player - 1] += 1;
+        self.kick_off = self.next_player(scoring_player);
+
+        self.end_point(scoring_player);
+    }
+
+    /// Settles the end of a rally won by `scorer`: the next point, the next
+    /// game of the series or the end of the match.
+    fn end_point(&mut self, scorer: usize) {
+        let time_up = self.time_left() == Some(0.0);
+
+        let Some(winner) =
+            self.config
+                .rules
+                .game_winner(self.two_player_score(), time_up, self.overtime)
+        else {
+            self.enter(Phase::PointScored {
+                scorer,
+                game_won: false,
+            });
+            return;
+        };
+
+        self.games_won[winner - 1] += 1;
+        if self.games_won[winner - 1] >= self.config.rules.games_to_win() {
+            self.winner = winner;
+            self.events.push(GameEvent::GameOver { winner });
+            self.enter(Phase::GameOver);
+            return;
+        }
+
+        // The loser of a game kicks off the next one.
+        self.clear_effects();
+        self.score.fill(0);
+        self.game_time = 0.0;
+        self.overtime = false;
+        self.kick_off = self.next_player(winner);
+        self.enter(Phase::PointScored {
+            scorer: winner,
+            game_won: true,
+        });
+    }
+
+    /// Starts the match over with the same players and settings.
+    fn rematch(&mut self) {
+        self.clear_effects();
+        self.score.fill(0);
+        self.games_won.fill(0);
+        self.lives.fill(self.config.rules.lives);
+        self.game_time = 0.0;
+        self.overtime = false;
+        self.winner = 0;
+        self.kick_off = 1;
+        self.time_to_spawn_power_up = self.config.powerups.spawn_interval;
+        self.reset_for_kick_off();
+        self.enter(Phase::Serve);
+    }
+
+    /// Puts paddles and ball back in place for `kick_off` to start play.
+    fn reset_for_kick_off(&mut self) {
+        // Left-over balls from a multi-ball rally go; the first is served.
+        self.balls.truncate(1);
+        self.balls[0].speed.base = 0.0;
+        self.balls[0].spin = 0.0;
+
+        for player in &mut self.players {
+            player.reset(&self.config.paddle);
+        }
+
+        self.serve_aim = 0.0;
+        self.attach_ball_to_server();
+
+        self.active_powerups.clear();
+    }
+
+    fn spawn_power_up(&mut self) {
+        let rng = &mut self.rng;
+        let definitions = &self.config.powerups.definitions;
+
+        // Every config a match can start with, whether from a file, a replay
+        // or another machine, has been through validation.
+        let kind = WeightedIndex::new(definitions.iter().map(|def| def.weight))
+            .expect("validated in Config::validate")
+            .sample(rng);
+
+        let spawn_x = rng.random_range(SPRITE_SPAWN_MARGIN..WIDTH - SPRITE_SPAWN_MARGIN);
+        let spawn_y = rng.random_range(50.0..HEIGHT - 50.0);
+
+        self.active_powerups
+            .push(PowerUp::new(spawn_x, spawn_y, kind));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::PaddleInput;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.load_power_ups().unwrap();
+        config
+    }
+
+    fn paddle(axis: f64, serve: bool) -> PaddleInput {
+        PaddleInput { axis, serve }
+    }
+
+    /// Player 1 aims their serve, launches it and then heads back up while
+    /// player 2 moves to meet the ball.
+    fn scripted_inputs(time: f64) -> Inputs {
+        // Every tick rate tested lands on each boundary exactly; rounding
+        // must not move one a tick earlier or later.
+        let time = time + 1e-9;
+        let mut inputs = Inputs::default();
+        if time < 0.3 {
+            inputs.paddles[0] = paddle(1.0, false);
+        } else if time < 0.35 {
+            inputs.paddles[0] = paddle(0.0, true);
+        } else {
+            inputs.paddles[0] = paddle(-0.5, false);
+            inputs.paddles[1] = paddle(-1.0, false);
+        }
+        inputs
+    }
+
+    fn run_scripted(tick_rate: f64, seconds: f64) -> GameState {
+        let mut config = config();
+        config.tick_rate = tick_rate;
+        // Power-ups would land wherever the dice say; keep them out.
+        config.powerups.spawn_interval = 1e9;
+
+        let dt = 1.0 / tick_rate;
+        let mut game = GameState::new(1, config);
+        game.start();
+        let ticks = (seconds * tick_rate).round() as u32;
+        for tick in 0..ticks {
+            game.step(dt, &scripted_inputs(tick as f64 * dt));
+        }
+        game
+    }
+
+    #[test]
+    fn tick_rate_does_not_change_the_match() {
+        let reference = run_scripted(60.0, 1.5);
+        assert_eq!(reference.phase, Phase::Rally);
+
+        for tick_rate in [120.0, 240.0] {
+            let game = run_scripted(tick_rate, 1.5);
+            assert_eq!(game.phase, reference.phase);
+
+            for (a, b) in game.players.iter().zip(&reference.players) {
+                assert!(
+                    (a.along() - b.along()).abs() < 1.0,
+                    "paddle at {} ticks per second: {} vs {}",
+                    tick_rate,
+                    a.along(),
+                    b.along()
+                );
+            }
+            // The serve launches on a tick boundary, so the ball may leave up
+            // to one tick of the slowest rate later.
+            let tolerance = reference.config.ball.serve_speed / 60.0;
+            let (a, b) = (&game.balls[0].position, &reference.balls[0].position);
+            assert!(
+                (a.x - b.x).abs() < tolerance && (a.y - b.y).abs() < tolerance,
+                "ball at {tick_rate} ticks per second: ({}, {}) vs ({}, {})",
+                a.x,
+                a.y,
+                b.x,
+                b.y
+            );
+        }
+    }
+
+    const DT: f64 = 1.0 / 120.0;
+
+    fn game_with(change: impl FnOnce(&mut Config)) -> GameState {
+        let mut config = config();
+        change(&mut config);
+        GameState::new(1, config)
+    }
+
+    /// Runs one tick with `change` applied to otherwise idle inputs.
+    fn tick(game: &mut GameState, change: impl FnOnce(&mut Inputs)) {
+        let mut inputs = Inputs::default();
+        change(&mut inputs);
+        game.step(DT, &inputs);
+    }
+
+    fn idle(game: &mut GameState, seconds: f64) {
+        for _ in 0..(seconds / DT).ceil() as u32 {
+            tick(game, |_| {});
+        }
+    }
+
+    /// Waits out the break after a point.
+    fn next_serve(game: &mut GameState) {
+        // One tick over, as the ticks may add up to a hair under the delay.
+        idle(game, POINT_SCORED_DELAY + DT);
+    }
+
+    /// Has the kick-off player launch the ball.
+    fn serve(game: &mut GameState) {
+        assert_eq!(game.phase, Phase::Serve);
+        let server = game.kick_off;
+        tick(game, |inputs| inputs.paddles[server - 1].serve = true);
+        assert_eq!(game.phase, Phase::Rally);
+    }
+
+    /// Puts the ball in `player`'s goal and runs the tick that scores it.
+    fn concede(game: &mut GameState, player: usize) {
+        assert_eq!(game.phase, Phase::Rally);
+        let side = game.player(player).side;
+        let (x, y) = match side {
+            Side::Left => (1.0, HEIGHT / 2.0),
+            Side::Right => (WIDTH - 1.0, HEIGHT / 2.0),
+            Side::Top => (WIDTH / 2.0, 1.0),
+            Side::Bottom => (WIDTH / 2.0, HEIGHT - 1.0),
+        };
+        let ball = &mut game.balls[0];
+        ball.position = Position { x, y };
+        ball.angle = side.normal_angle() + std::f64::consts::PI;
+        tick(game, |_| {});
+    }
+
+    /// Serves, lets `player` concede and waits for the next serve.
+    fn play_point(game: &mut GameState, player: usize) {
+        serve(game);
+        concede(game, player);
+        if game.phase != Phase::GameOver {
+            next_serve(game);
+        }
+    }
+
+    #[test]
+    fn start_leaves_the_title_screen() {
+        let mut game = game_with(|_| {});
+        idle(&mut game, 1.0);
+        assert_eq!(game.phase, Phase::Title);
+
+        tick(&mut game, |inputs| inputs.start = true);
+        assert_eq!(game.phase, Phase::Serve);
+    }
+
+    #[test]
+    fn serve_launches_the_ball() {
+        let mut game = game_with(|_| {});
+        game.start();
+        assert_eq!(game.balls[0].speed(), 0.0);
+
+        serve(&mut game);
+        assert_eq!(game.balls[0].speed(), game.config.ball.serve_speed);
+        assert_eq!(game.balls[0].last_hit, 1);
+
+        let before = game.balls[0].position.x;
+        tick(&mut game, |_| {});
+        assert!(game.balls[0].position.x > before);
+    }
+
+    #[test]
+    fn the_ball_serves_itself_after_the_auto_serve_time() {
+        let mut game = game_with(|config| config.ball.auto_serve_time = 0.5);
+        game.start();
+        idle(&mut game, 0.4);
+        assert_eq!(game.phase, Phase::Serve);
+        idle(&mut game, 0.2);
+        assert_eq!(game.phase, Phase::Rally);
+    }
+
+    #[test]
+    fn a_goal_scores_for_the_other_player() {
+        let mut game = game_with(|_| {});
+        game.start();
+        serve(&mut game);
+        concede(&mut game, 1);
+
+        assert_eq!(game.score, [0, 1]);
+        assert_eq!(
+            game.phase,
+            Phase::PointScored {
+                scorer: 2,
+                game_won: false
+            }
+        );
+        // The scorer's opponent serves next, once the break is over.
+        assert_eq!(game.kick_off, 1);
+        next_serve(&mut game);
+        assert_eq!(game.phase, Phase::Serve);
+    }
+
+    #[test]
+    fn reaching_the_target_score_ends_the_match() {
+        let mut game = game_with(|config| config.rules.target_score = 2);
+        game.start();
+        play_point(&mut game, 2);
+        assert_eq!(game.phase, Phase::Serve);
+        play_point(&mut game, 2);
+
+        assert_eq!(game.phase, Phase::GameOver);
+        assert_eq!(game.score, [2, 0]);
+        assert_eq!(game.winner, 1);
+        assert!(matches!(game.events(), [GameEvent::GameOver { winner: 1 }]));
+    }
+
+    #[test]
+    fn winning_a_game_of_a_series_starts_the_next() {
+        let mut game = game_with(|config| {
+            config.rules.target_score = 1;
+            config.rules.best_of = 3;
+        });
+        game.start();
+        serve(&mut game);
+        concede(&mut game, 1);
+
+        assert_eq!(
+            game.phase,
+            Phase::PointScored {
+                scorer: 2,
+                game_won: true
+            }
+        );
+        assert_eq!(game.games_won, [0, 1]);
+        assert_eq!(game.score, [0, 0]);
+        // The loser of a game kicks off the next.
+        assert_eq!(game.kick_off, 1);
+
+        next_serve(&mut game);
+        play_point(&mut game, 1);
+        assert_eq!(game.phase, Phase::GameOver);
+        assert_eq!(game.winner, 2);
+    }
+
+    #[test]
+    fn time_up_ends_the_game_for_the_leader() {
+        let mut game = game_with(|config| config.rules.time_limit = Some(1.0));
+        game.start();
+        play_point(&mut game, 2);
+        serve(&mut game);
+
+        // Bouncing between the top and bottom walls until time runs out.
+        game.balls[0].position = Position {
+            x: WIDTH / 2.0,
+            y: HEIGHT / 2.0,
+        };
+        game.balls[0].angle = std::f64::consts::FRAC_PI_2;
+        idle(&mut game, 1.1);
+
+        assert_eq!(game.phase, Phase::GameOver);
+        assert_eq!(game.winner, 1);
+    }
+
+    #[test]
+    fn a_level_score_at_time_up_goes_to_overtime() {
+        let mut game = game_with(|config| config.rules.time_limit = Some(0.5));
+        game.start();
+        serve(&mut game);
+        game.balls[0].position = Position {
+            x: WIDTH / 2.0,
+            y: HEIGHT / 2.0,
+        };
+        game.balls[0].angle = std::f64::consts::FRAC_PI_2;
+        idle(&mut game, 1.0);
+
+        assert!(game.overtime);
+        assert_eq!(game.phase, Phase::Rally);
+
+        // Golden point.
+        concede(&mut game, 2);
+        assert_eq!(game.phase, Phase::GameOver);
+        assert_eq!(game.winner, 1);
+    }
+
+    #[test]
+    fn rematch_resets_the_match() {
+        let mut game = game_with(|config| config.rules.target_score = 1);
+        game.start();
+        play_point(&mut game, 1);
+        assert_eq!(game.phase, Phase::GameOver);
+
+        tick(&mut game, |inputs| inputs.rematch = true);
+        assert_eq!(game.phase, Phase::Serve);
+        assert_eq!(game.score, [0, 0]);
+        assert_eq!(game.games_won, [0, 0]);
+        assert_eq!(game.winner, 0);
+        assert_eq!(game.kick_off, 1);
+    }
+
+    #[test]
+    fn quit_on_the_game_over_screen_is_requested() {
+        let mut game = game_with(|config| config.rules.target_score = 1);
+        game.start();
+        play_point(&mut game, 1);
+
+        tick(&mut game, |inputs| inputs.quit = true);
+        assert!(game.quit_requested);
+    }
+
+    #[test]
+    fn pause_freezes_the_rally_until_pressed_again() {
+        let mut game = game_with(|_| {});
+        game.start();
+        serve(&mut game);
+
+        tick(&mut game, |inputs| inputs.pause = true);
+        assert_eq!(game.phase, Phase::Paused);
+        assert_eq!(game.playing_phase(), Phase::Rally);
+
+        let before = game.balls[0].position.x;
+        idle(&mut game, 0.5);
+        assert_eq!(game.balls[0].position.x, before);
+
+        tick(&mut game, |inputs| inputs.pause = true);
+        assert_eq!(game.phase, Phase::Rally);
+        tick(&mut game, |_| {});
+        assert!(game.balls[0].position.x > before);
+    }
+
+    #[test]
+    fn four_players_lose_lives_until_one_is_left() {
+        let mut game = game_with(|config| {
+            config.rules.players = 4;
+            config.rules.lives = 2;
+        });
+        game.start();
+
+        serve(&mut game);
+        concede(&mut game, 1);
+        assert_eq!(
+            game.phase,
+            Phase::LifeLost {
+                player: 1,
+                eliminated: false
+            }
+        );
+        assert_eq!(game.lives, [1, 2, 2, 2]);
+        // Whoever let the goal in serves next.
+        assert_eq!(game.kick_off, 1);
+        next_serve(&mut game);
+
+        serve(&mut game);
+        concede(&mut game, 1);
+        assert_eq!(
+            game.phase,
+            Phase::LifeLost {
+                player: 1,
+                eliminated: true
+            }
+        );
+        assert!(game.is_out(1));
+        assert_eq!(game.walls(), [Side::Left]);
+        assert_eq!(game.kick_off, 2);
+        next_serve(&mut game);
+
+        for player in [2, 3] {
+            play_point(&mut game, player);
+            play_point(&mut game, player);
+        }
+        assert_eq!(game.phase, Phase::GameOver);
+        assert_eq!(game.winner, 4);
+        assert_eq!(game.lives, [0, 0, 0, 2]);
+    }
+}
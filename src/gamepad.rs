@@ -0,0 +1,319 @@
+use gilrs::{Axis, Button, Event, EventType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::constants::MAX_PLAYERS;
+use crate::input::{Inputs, PaddleInput};
+
+/// Which controller buttons and axis drive a paddle, named the way gilrs
+/// names them.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GamepadConfig {
+    /// Stick movements smaller than this, from 0 to 1, are ignored.
+    pub dead_zone: f64,
+    /// Stick axis that moves the paddle; pushing it down moves the paddle
+    /// down.
+    pub axis: Axis,
+    /// Stick axis that moves a paddle on the top or bottom of the field;
+    /// pushing it right moves the paddle right.
+    pub cross_axis: Axis,
+    pub up_button: Button,
+    pub down_button: Button,
+    pub left_button: Button,
+    pub right_button: Button,
+    pub serve_button: Button,
+    /// Starts the match from the title screen, and pauses once it's on.
+    pub start_button: Button,
+    pub rematch_button: Button,
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        GamepadConfig {
+            dead_zone: 0.2,
+            axis: Axis::LeftStickY,
+            cross_axis: Axis::LeftStickX,
+            up_button: Button::DPadUp,
+            down_button: Button::DPadDown,
+            left_button: Button::DPadLeft,
+            right_button: Button::DPadRight,
+            serve_button: Button::South,
+            start_button: Button::Start,
+            rematch_button: Button::North,
+        }
+    }
+}
+
+/// A controller taking or giving up a player, for the front-end to report.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PadChange {
+    Joined { id: usize, player: usize },
+    Left { id: usize, player: usize },
+}
+
+/// What one controller is currently doing.
+#[derive(Default)]
+struct Pad {
+    id: usize,
+    /// Stick position on `axis` and `cross_axis` after the dead zone, each
+    /// from -1.0 to 1.0 with down and right positive.
+    axes: [f64; 2],
+    buttons: HashSet<Button>,
+}
+
+/// Controllers and the players they belong to.
+///
+/// A controller is given to the first free player the first time it is
+/// used, so one plugged in mid-match joins without a restart, and gives the
+/// player up again when it is unplugged. Events from gilrs are fed in one
+/// at a time and turned into `Inputs`; the tests feed in the buttons and
+/// axes directly, as gilrs controller ids can't be made up.
+pub struct Gamepads {
+    config: GamepadConfig,
+    /// Controller for each player, if any.
+    pads: [Option<Pad>; MAX_PLAYERS],
+    /// Players a controller may be given to, by their input source.
+    open: [bool; MAX_PLAYERS],
+}
+
+impl Gamepads {
+    pub fn new(config: GamepadConfig, open: [bool; MAX_PLAYERS]) -> Self {
+        Gamepads {
+            config,
+            pads: Default::default(),
+            open,
+        }
+    }
+
+    /// Player the controller `id` belongs to, from 1.
+    pub fn player(&self, id: usize) -> Option<usize> {
+        self.pads
+            .iter()
+            .position(|pad| pad.as_ref().is_some_and(|pad| pad.id == id))
+            .map(|index| index + 1)
+    }
+
+    /// Feeds in an event from gilrs. Returns the change if it made a
+    /// controller join or leave a player.
+    pub fn event(&mut self, event: &Event) -> Option<PadChange> {
+        let id = usize::from(event.id);
+
+        match event.event {
+            EventType::ButtonPressed(button, _) => self.button(id, button, true),
+            EventType::ButtonReleased(button, _) => self.button(id, button, false),
+            EventType::AxisChanged(axis, position, _) => self.axis(id, axis, f64::from(position)),
+            EventType::Disconnected => self.disconnect(id),
+            _ => None,
+        }
+    }
+
+    fn button(&mut self, id: usize, button: Button, pressed: bool) -> Option<PadChange> {
+        // Letting go of a button can't claim a player.
+        let (pad, change) = self.pad_mut(id, pressed)?;
+
+        if pressed {
+            pad.buttons.insert(button);
+        } else {
+            pad.buttons.remove(&button);
+        }
+        change
+    }
+
+    fn axis(&mut self, id: usize, axis: Axis, position: f64) -> Option<PadChange> {
+        let index = if axis == self.config.axis {
+            0
+        } else if axis == self.config.cross_axis {
+            1
+        } else {
+            return None;
+        };
+
+        let position = apply_dead_zone(screen_direction(axis) * position, self.config.dead_zone);
+        // A stick resting near the middle isn't someone picking it up.
+        let (pad, change) = self.pad_mut(id, position != 0.0)?;
+        pad.axes[index] = position;
+        change
+    }
+
+    /// Frees the player controller `id` belonged to, so the next controller
+    /// used takes over, even the same one back under a new id.
+    fn disconnect(&mut self, id: usize) -> Option<PadChange> {
+        let player = self.player(id)?;
+        self.pads[player - 1] = None;
+        Some(PadChange::Left { id, player })
+    }
+
+    /// What `player`'s controller, if any, is doing to the paddle. Paddles
+    /// that aren't `vertical` move with the cross axis and the left and
+    /// right buttons.
+    pub fn paddle_input(&self, player: usize, vertical: bool) -> PaddleInput {
+        let config = &self.config;
+        let Some(pad) = &self.pads[player - 1] else {
+            return PaddleInput::default();
+        };
+        let held = |button| pad.buttons.contains(&button);
+
+        let (mut axis, back, forward) = if vertical {
+            (pad.axes[0], config.up_button, config.down_button)
+        } else {
+            (pad.axes[1], config.left_button, config.right_button)
+        };
+        if held(back) {
+            axis -= 1.0;
+        }
+        if held(forward) {
+            axis += 1.0;
+        }
+
+        PaddleInput {
+            axis,
+            serve: held(config.serve_button),
+        }
+    }
+
+    /// Adds the controllers' start, pause and rematch buttons to `inputs`.
+    pub fn apply_buttons(&self, inputs: &mut Inputs) {
+        let config = &self.config;
+
+        for pad in self.pads.iter().flatten() {
+            let held = |button| pad.buttons.contains(&button);
+
+            inputs.start |= held(config.start_button);
+            inputs.pause |= held(config.start_button);
+            inputs.rematch |= held(config.rematch_button);
+        }
+    }
+
+    /// The pad for controller `id`, giving it to a free player first if
+    /// `claim` is set, along with the change if it did.
+    fn pad_mut(&mut self, id: usize, claim: bool) -> Option<(&mut Pad, Option<PadChange>)> {
+        let (index, change) = match self.player(id) {
+            Some(player) => (player - 1, None),
+            None if claim => {
+                let index = (0..MAX_PLAYERS).find(|&i| self.open[i] && self.pads[i].is_none())?;
+                self.pads[index] = Some(Pad {
+                    id,
+                    ..Pad::default()
+                });
+                let player = index + 1;
+                (index, Some(PadChange::Joined { id, player }))
+            }
+            None => return None,
+        };
+        Some((self.pads[index].as_mut()?, change))
+    }
+}
+
+/// What to multiply a position on `axis` by so down and right count as
+/// positive, the way the paddles move: gilrs counts up as positive.
+fn screen_direction(axis: Axis) -> f64 {
+    match axis {
+        Axis::LeftStickY | Axis::RightStickY | Axis::DPadY => -1.0,
+        _ => 1.0,
+    }
+}
+
+/// Maps a stick position so the dead zone reads as 0 and the rest of the
+/// travel still covers the full -1.0 to 1.0.
+fn apply_dead_zone(position: f64, dead_zone: f64) -> f64 {
+    let magnitude = position.abs().min(1.0);
+    if magnitude <= dead_zone {
+        return 0.0;
+    }
+    position.signum() * (magnitude - dead_zone) / (1.0 - dead_zone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::assert_close;
+
+    fn gamepads(open: [bool; MAX_PLAYERS]) -> Gamepads {
+        Gamepads::new(GamepadConfig::default(), open)
+    }
+
+    fn press(gamepads: &mut Gamepads, id: usize, button: Button) -> Option<PadChange> {
+        gamepads.button(id, button, true)
+    }
+
+    fn push(gamepads: &mut Gamepads, id: usize, axis: Axis, position: f64) {
+        gamepads.axis(id, axis, position);
+    }
+
+    #[test]
+    fn a_button_claims_the_first_open_player() {
+        let mut gamepads = gamepads([false, true, true, false]);
+
+        let change = press(&mut gamepads, 7, Button::DPadDown);
+        assert_eq!(change, Some(PadChange::Joined { id: 7, player: 2 }));
+        assert_eq!(gamepads.player(7), Some(2));
+        assert_close(gamepads.paddle_input(2, true).axis, 1.0);
+        assert_eq!(gamepads.paddle_input(1, true).axis, 0.0);
+
+        // Pressing again doesn't join a second time.
+        assert_eq!(press(&mut gamepads, 7, Button::DPadUp), None);
+
+        press(&mut gamepads, 8, Button::South);
+        assert_eq!(gamepads.player(8), Some(3));
+        assert!(gamepads.paddle_input(3, false).serve);
+
+        // Every open player is taken.
+        assert_eq!(press(&mut gamepads, 9, Button::South), None);
+        assert_eq!(gamepads.player(9), None);
+    }
+
+    #[test]
+    fn releasing_a_button_or_resting_the_stick_claims_nobody() {
+        let mut gamepads = gamepads([true; MAX_PLAYERS]);
+
+        gamepads.button(1, Button::South, false);
+        push(&mut gamepads, 1, Axis::LeftStickY, 0.1);
+        assert_eq!(gamepads.player(1), None);
+    }
+
+    #[test]
+    fn the_stick_is_rescaled_past_the_dead_zone() {
+        let mut gamepads = gamepads([true; MAX_PLAYERS]);
+
+        // gilrs counts up as positive; the paddle moves up.
+        push(&mut gamepads, 1, Axis::LeftStickY, 0.6);
+        assert_eq!(gamepads.player(1), Some(1));
+        assert_close(gamepads.paddle_input(1, true).axis, -0.5);
+
+        push(&mut gamepads, 1, Axis::LeftStickY, -1.0);
+        assert_close(gamepads.paddle_input(1, true).axis, 1.0);
+
+        push(&mut gamepads, 1, Axis::LeftStickY, 0.15);
+        assert_eq!(gamepads.paddle_input(1, true).axis, 0.0);
+
+        // Top and bottom paddles follow the cross axis instead.
+        push(&mut gamepads, 1, Axis::LeftStickX, -0.6);
+        assert_close(gamepads.paddle_input(1, false).axis, -0.5);
+        assert_eq!(gamepads.paddle_input(1, true).axis, 0.0);
+
+        // Other axes are ignored.
+        push(&mut gamepads, 1, Axis::RightStickX, 1.0);
+        assert_close(gamepads.paddle_input(1, false).axis, -0.5);
+    }
+
+    #[test]
+    fn disconnecting_frees_the_player() {
+        let mut gamepads = gamepads([true, true, false, false]);
+
+        press(&mut gamepads, 3, Button::South);
+        press(&mut gamepads, 4, Button::South);
+        assert_eq!(
+            gamepads.disconnect(3),
+            Some(PadChange::Left { id: 3, player: 1 })
+        );
+        assert_eq!(gamepads.player(3), None);
+        assert!(!gamepads.paddle_input(1, true).serve);
+        assert_eq!(gamepads.disconnect(3), None);
+
+        // Plugged back in under a new id, it takes its old player back.
+        press(&mut gamepads, 5, Button::South);
+        assert_eq!(gamepads.player(5), Some(1));
+        assert_eq!(gamepads.player(4), Some(2));
+    }
+}
@@ -0,0 +1,339 @@
+use piston::Key;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::constants::MAX_PLAYERS;
+
+/// Opens the key bindings menu from the title screen, and closes it, so it
+/// can't be bound to an action.
+pub const MENU_KEY: Key = Key::Tab;
+
+/// Something a key can be bound to.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    P1Up,
+    P1Down,
+    P1Serve,
+    P2Up,
+    P2Down,
+    P2Serve,
+    P3Left,
+    P3Right,
+    P3Serve,
+    P4Left,
+    P4Right,
+    P4Serve,
+    Start,
+    Pause,
+    Step,
+    Rematch,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 17] = [
+        Action::P1Up,
+        Action::P1Down,
+        Action::P1Serve,
+        Action::P2Up,
+        Action::P2Down,
+        Action::P2Serve,
+        Action::P3Left,
+        Action::P3Right,
+        Action::P3Serve,
+        Action::P4Left,
+        Action::P4Right,
+        Action::P4Serve,
+        Action::Start,
+        Action::Pause,
+        Action::Step,
+        Action::Rematch,
+        Action::Quit,
+    ];
+
+    /// The two movement actions and the serve action of `player`.
+    pub fn paddle_actions(player: usize) -> [Action; 3] {
+        match player {
+            1 => [Action::P1Up, Action::P1Down, Action::P1Serve],
+            2 => [Action::P2Up, Action::P2Down, Action::P2Serve],
+            3 => [Action::P3Left, Action::P3Right, Action::P3Serve],
+            _ => [Action::P4Left, Action::P4Right, Action::P4Serve],
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::P1Up => "PLAYER 1 UP",
+            Action::P1Down => "PLAYER 1 DOWN",
+            Action::P1Serve => "PLAYER 1 SERVE",
+            Action::P2Up => "PLAYER 2 UP",
+            Action::P2Down => "PLAYER 2 DOWN",
+            Action::P2Serve => "PLAYER 2 SERVE",
+            Action::P3Left => "PLAYER 3 LEFT",
+            Action::P3Right => "PLAYER 3 RIGHT",
+            Action::P3Serve => "PLAYER 3 SERVE",
+            Action::P4Left => "PLAYER 4 LEFT",
+            Action::P4Right => "PLAYER 4 RIGHT",
+            Action::P4Serve => "PLAYER 4 SERVE",
+            Action::Start => "START",
+            Action::Pause => "PAUSE",
+            Action::Step => "STEP ONE TICK",
+            Action::Rematch => "REMATCH",
+            Action::Quit => "QUIT",
+        }
+    }
+}
+
+/// The key for each action. Stored in the config as a map from action to
+/// key; actions left out keep their default key.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "BTreeMap<Action, Key>", into = "BTreeMap<Action, Key>")]
+pub struct KeyBindings {
+    keys: BTreeMap<Action, Key>,
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: Action) -> Key {
+        self.keys[&action]
+    }
+
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.keys
+            .iter()
+            .find(|(_, bound)| **bound == key)
+            .map(|(action, _)| *action)
+    }
+
+    /// Binds `key` to `action`. If another action had that key, it takes
+    /// over `action`'s old key instead, and is returned.
+    pub fn bind(&mut self, action: Action, key: Key) -> Option<Action> {
+        let old_key = self.key(action);
+        let other = self.action(key).filter(|other| *other != action);
+
+        if let Some(other) = other {
+            self.keys.insert(other, old_key);
+        }
+        self.keys.insert(action, key);
+        other
+    }
+
+    /// Fails if one key is bound to two actions, or to the menu.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(action) = self.action(MENU_KEY) {
+            return Err(format!(
+                "{} opens the key menu and can't be used for {}",
+                key_name(MENU_KEY),
+                action.label()
+            ));
+        }
+        for (i, first) in Action::ALL.iter().enumerate() {
+            for second in &Action::ALL[i + 1..] {
+                let key = self.key(*first);
+                if key == self.key(*second) {
+                    return Err(format!(
+                        "{} is bound to both {} and {}",
+                        key_name(key),
+                        first.label(),
+                        second.label()
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let keys = [
+            (Action::P1Up, Key::W),
+            (Action::P1Down, Key::S),
+            (Action::P1Serve, Key::D),
+            (Action::P2Up, Key::Up),
+            (Action::P2Down, Key::Down),
+            (Action::P2Serve, Key::Left),
+            (Action::P3Left, Key::J),
+            (Action::P3Right, Key::L),
+            (Action::P3Serve, Key::K),
+            (Action::P4Left, Key::NumPad4),
+            (Action::P4Right, Key::NumPad6),
+            (Action::P4Serve, Key::NumPad5),
+            (Action::Start, Key::Space),
+            (Action::Pause, Key::P),
+            (Action::Step, Key::N),
+            (Action::Rematch, Key::R),
+            (Action::Quit, Key::Q),
+        ];
+        KeyBindings {
+            keys: keys.into_iter().collect(),
+        }
+    }
+}
+
+impl From<BTreeMap<Action, Key>> for KeyBindings {
+    fn from(keys: BTreeMap<Action, Key>) -> Self {
+        let mut bindings = KeyBindings::default();
+        bindings.keys.extend(keys);
+        bindings
+    }
+}
+
+impl From<KeyBindings> for BTreeMap<Action, Key> {
+    fn from(bindings: KeyBindings) -> Self {
+        bindings.keys
+    }
+}
+
+/// Name of `key` as shown on screen.
+pub fn key_name(key: Key) -> String {
+    let name = format!("{key:?}").to_uppercase();
+
+    // Number row keys are called D0 to D9.
+    match name.strip_prefix('D') {
+        Some(digit) if digit.len() == 1 && digit.chars().all(|c| c.is_ascii_digit()) => {
+            digit.to_string()
+        }
+        _ => name,
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PaddleInput {
+    /// Movement from -1.0 (full speed up, or left for the top and bottom
+    /// paddles) to 1.0 (full speed down, or right).
+    pub axis: f64,
+    /// Launches the ball when this player is serving.
+    pub serve: bool,
+}
+
+impl PaddleInput {
+    /// Combines two devices driving the same paddle.
+    pub fn add(&mut self, other: PaddleInput) {
+        self.axis += other.axis;
+        self.serve |= other.serve;
+    }
+
+    fn pressed_since(&self, previous: &PaddleInput) -> PaddleInput {
+        PaddleInput {
+            axis: self.axis,
+            serve: self.serve && !previous.serve,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Inputs {
+    /// One per player, in player order. Two-player matches leave the last
+    /// two idle.
+    pub paddles: [PaddleInput; MAX_PLAYERS],
+    pub start: bool,
+    pub pause: bool,
+    /// Advances one tick while paused, for debugging.
+    pub step: bool,
+    pub rematch: bool,
+    pub quit: bool,
+}
+
+impl Inputs {
+    pub fn from_keys(keys: &HashSet<Key>, bindings: &KeyBindings) -> Self {
+        let mut inputs = Inputs::default();
+
+        for action in keys.iter().filter_map(|key| bindings.action(*key)) {
+            match action {
+                Action::P1Up => inputs.paddles[0].axis -= 1.0,
+                Action::P1Down => inputs.paddles[0].axis += 1.0,
+                Action::P1Serve => inputs.paddles[0].serve = true,
+                Action::P2Up => inputs.paddles[1].axis -= 1.0,
+                Action::P2Down => inputs.paddles[1].axis += 1.0,
+                Action::P2Serve => inputs.paddles[1].serve = true,
+                Action::P3Left => inputs.paddles[2].axis -= 1.0,
+                Action::P3Right => inputs.paddles[2].axis += 1.0,
+                Action::P3Serve => inputs.paddles[2].serve = true,
+                Action::P4Left => inputs.paddles[3].axis -= 1.0,
+                Action::P4Right => inputs.paddles[3].axis += 1.0,
+                Action::P4Serve => inputs.paddles[3].serve = true,
+                Action::Start => inputs.start = true,
+                Action::Pause => inputs.pause = true,
+                Action::Step => inputs.step = true,
+                Action::Rematch => inputs.rematch = true,
+                Action::Quit => inputs.quit = true,
+            }
+        }
+
+        inputs
+    }
+
+    pub fn paddle(&self, player: usize) -> PaddleInput {
+        self.paddles[player - 1]
+    }
+
+    pub fn paddle_mut(&mut self, player: usize) -> &mut PaddleInput {
+        &mut self.paddles[player - 1]
+    }
+
+    /// Adds the buttons held in `other` that aren't tied to a paddle.
+    pub fn merge_buttons(&mut self, other: &Inputs) {
+        self.start |= other.start;
+        self.pause |= other.pause;
+        self.step |= other.step;
+        self.rematch |= other.rematch;
+        self.quit |= other.quit;
+    }
+
+    /// These inputs with the buttons that were already held in `previous`
+    /// released, so each press only counts once.
+    pub fn pressed_since(&self, previous: &Inputs) -> Inputs {
+        Inputs {
+            paddles: std::array::from_fn(|i| self.paddles[i].pressed_since(&previous.paddles[i])),
+            start: self.start && !previous.start,
+            pause: self.pause && !previous.pause,
+            step: self.step && !previous.step,
+            rematch: self.rematch && !previous.rematch,
+            quit: self.quit && !previous.quit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_swaps_with_the_action_that_had_the_key() {
+        let mut bindings = KeyBindings::default();
+
+        assert_eq!(bindings.bind(Action::P1Up, Key::Up), Some(Action::P2Up));
+        assert_eq!(bindings.key(Action::P1Up), Key::Up);
+        assert_eq!(bindings.key(Action::P2Up), Key::W);
+        assert!(bindings.validate().is_ok());
+    }
+
+    #[test]
+    fn bind_to_a_free_key_or_its_own_key_swaps_nothing() {
+        let mut bindings = KeyBindings::default();
+
+        assert_eq!(bindings.bind(Action::P1Up, Key::F1), None);
+        assert_eq!(bindings.key(Action::P1Up), Key::F1);
+        assert_eq!(bindings.bind(Action::P1Up, Key::F1), None);
+        assert_eq!(bindings.key(Action::P1Up), Key::F1);
+        assert!(bindings.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_a_key_bound_twice() {
+        let mut bindings = KeyBindings::default();
+        bindings.keys.insert(Action::P2Serve, Key::D);
+
+        let err = bindings.validate().unwrap_err();
+        assert!(err.contains("bound to both"), "{err}");
+    }
+
+    #[test]
+    fn validate_rejects_the_menu_key() {
+        let mut bindings = KeyBindings::default();
+        bindings.bind(Action::Pause, MENU_KEY);
+
+        let err = bindings.validate().unwrap_err();
+        assert!(err.contains("opens the key menu"), "{err}");
+    }
+}
@@ -1,3 +1,4 @@
+use gilrs::Gilrs;
 use glutin_window::GlutinWindow as Window;
 use opengl_graphics::{GlGraphics, OpenGL};
 use piston::Button;
@@ -6,12 +7,11 @@ use piston::PressEvent;
 use piston::ReleaseEvent;
 use piston::event_loop::{EventSettings, Events};
 use piston::input::{RenderArgs, RenderEvent, UpdateArgs, UpdateEvent};
-use piston::window::Window as WindowTrait;
 use piston::window::WindowSettings;
-use rand::Rng;
-use rand::seq::IndexedRandom;
+use piston::window::{AdvancedWindow, Window as WindowTrait};
 use std::collections::HashSet;
-use std::f64::consts::PI;
+use std::net::TcpListener;
+use std::path::PathBuf;
 
 mod number_renderer;
 use number_renderer::NumberRenderer;
@@ -33,224 +33,290 @@ use constants::*;
 
 mod object;
 
+mod collision;
+
+mod effect;
+
+mod stat;
+
+mod input;
+use input::{Inputs, KeyBindings, MENU_KEY};
+
+mod game;
+use game::{GameEvent, GameState, Phase};
+
+mod options;
+use options::Options;
+
+mod timestep;
+use timestep::FixedTimestep;
+
+mod replay;
+use replay::{Replay, ReplayPlayer};
+
+mod ai;
+
+mod config;
+use config::Config;
+
+mod rules;
+
+mod text;
+
+mod gamepad;
+use gamepad::{Gamepads, PadChange};
+
+mod mouse;
+use mouse::MouseMode;
+
+mod net;
+use net::PeerSource;
+
+mod source;
+use source::{InputSource, SourceSpec, TickContext, update_sources};
+
+mod menu;
+use menu::{BindingsMenu, MenuOutcome};
+
+mod render;
+use render::{Scene, WALL_FLASH_TIME, WallFlash};
+
+#[cfg(test)]
+mod testing;
+
+/// Longest match `--headless` will simulate, in seconds, in case neither
+/// side can score.
+const HEADLESS_TIME_LIMIT: f64 = 3600.0;
+
 pub struct App {
     gl: GlGraphics,
     number_renderer: NumberRenderer,
     powerup_sprites: PowerUpSprites,
-    active_powerups: Vec<Box<dyn PowerUp>>,
     pressed_keys: HashSet<Key>,
-    player1: Player,
-    player2: Player,
-    kick_off: usize,
-    ball: Ball,
-    game_over: bool,
-    score: [u32; 2],
-    winner: usize,
-    time_to_spawn_power_up: f64,
+    timestep: FixedTimestep,
+    game: GameState,
+    recording: Option<Replay>,
+    playback: Option<ReplayPlayer>,
+    playback_finished: bool,
+    /// What controls each player, player 1 first.
+    sources: Vec<Box<dyn InputSource>>,
+    wall_flashes: Vec<WallFlash>,
+    bindings: KeyBindings,
+    gamepads: Gamepads,
+    /// Where controller events come from; `None` if controllers can't be
+    /// read on this system.
+    gilrs: Option<Gilrs>,
+    bindings_menu: Option<BindingsMenu>,
+    /// Where changed key bindings are saved.
+    config_path: PathBuf,
 }
 
 impl App {
     fn render(&mut self, args: &RenderArgs) {
-        use graphics::*;
-
-        const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
-        const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
-
-        let ball = ellipse::circle(0.0, 0.0, self.ball.radius);
-
-        self.gl.draw(args.viewport(), |c, gl| {
-            clear(WHITE, gl);
-
-            let ball_transform = c
-                .transform
-                .trans(self.ball.position.x, self.ball.position.y);
-            let player1_transform = c
-                .transform
-                .trans(self.player1.position.x, self.player1.position.y);
-
-            let player2_transform = c
-                .transform
-                .trans(self.player2.position.x, self.player2.position.y);
-
-            let from = [0.0, 0.0];
-            let to_player1 = [self.player1.width, self.player1.height];
-            let to_player2 = [self.player2.width, self.player2.height];
-
-            ellipse(BLACK, ball, ball_transform, gl);
-            rectangle_from_to(BLACK, from, to_player1, player1_transform, gl);
-            rectangle_from_to(BLACK, from, to_player2, player2_transform, gl);
-
-            self.number_renderer.render(
-                self.score[0],
-                WIDTH / 4.0,
-                50.0,
-                100.0,
-                [0.0, 0.0, 0.0],
-                &c,
-                gl,
-            );
-
-            self.number_renderer.render(
-                self.score[1],
-                3.0 * WIDTH / 4.0,
-                50.0,
-                100.0,
-                [0.0, 0.0, 0.0],
-                &c,
-                gl,
-            );
-
-            for powerup in &self.active_powerups {
-                if powerup.collectable() {
-                    self.powerup_sprites.render(powerup.as_ref(), &c, gl);
-                }
-            }
-        });
+        let scene = Scene {
+            game: &self.game,
+            number_renderer: &self.number_renderer,
+            powerup_sprites: &self.powerup_sprites,
+            wall_flashes: &self.wall_flashes,
+            keys: &self.bindings,
+            bindings_menu: self.bindings_menu.as_ref(),
+        };
+
+        self.gl.draw(args.viewport(), |c, gl| scene.draw(&c, gl));
     }
 
     fn update(&mut self, args: &UpdateArgs) {
-        for key in &self.pressed_keys {
-            match key {
-                Key::W => self.player1.position.y -= self.player1.speed,
-                Key::S => self.player1.position.y += self.player1.speed,
-                Key::Up => self.player2.position.y -= self.player2.speed,
-                Key::Down => self.player2.position.y += self.player2.speed,
-                _ => {}
-            }
-            let player_num = match key {
-                Key::W | Key::S => 1,
-                Key::Up | Key::Down => 2,
-                _ => 0,
-            };
-            if player_num == self.kick_off {
-                self.ball.speed = 3.0;
+        if let Some(gilrs) = &mut self.gilrs {
+            while let Some(event) = gilrs.next_event() {
+                match self.gamepads.event(&event) {
+                    Some(PadChange::Joined { id, player }) => {
+                        println!("controller {id} joined as player {player}")
+                    }
+                    Some(PadChange::Left { id, player }) => {
+                        println!("controller {id} left player {player}")
+                    }
+                    None => {}
+                }
             }
         }
 
-        self.player1.prevent_out_of_bounds();
-        self.player2.prevent_out_of_bounds();
+        let mut live_inputs = Inputs::from_keys(&self.pressed_keys, &self.bindings);
+        self.gamepads.apply_buttons(&mut live_inputs);
 
-        self.ball.position.x += self.ball.angle.cos() * self.ball.speed;
-        self.ball.position.y -= self.ball.angle.sin() * self.ball.speed;
+        for _ in 0..self.timestep.advance(args.dt) {
+            if self.finished() {
+                break;
+            }
 
-        self.ball.check_and_handle_vertical_collision();
+            let inputs = match &mut self.playback {
+                Some(playback) => match playback.next_inputs() {
+                    Some(inputs) => inputs,
+                    None => {
+                        self.playback_finished = true;
+                        break;
+                    }
+                },
+                None => {
+                    let context = TickContext {
+                        game: &self.game,
+                        dt: self.timestep.tick(),
+                        keys: &self.pressed_keys,
+                        bindings: &self.bindings,
+                        gamepads: &self.gamepads,
+                    };
+                    let mut inputs = live_inputs;
+                    update_sources(&mut self.sources, &context, &mut inputs);
+                    inputs
+                }
+            };
 
-        if self.player1.collided(&self.ball) {
-            let collision_point = self.player1.collision_point(&self.ball);
-            self.ball.angle = -(75.0 * PI / 180.0) * collision_point;
-            self.ball.speed = 3.0 + collision_point.abs() * 3.0;
-            self.ball.last_hit = 1;
-        }
+            if let Some(recording) = &mut self.recording {
+                recording.record(&inputs);
+            }
 
-        if self.player2.collided(&self.ball) {
-            let collision_point = self.player2.collision_point(&self.ball);
-            self.ball.angle = PI + (75.0 * PI / 180.0) * collision_point;
-            self.ball.speed = 3.0 + collision_point.abs() * 3.0;
-            self.ball.last_hit = 2;
-        }
+            self.game.step(self.timestep.tick(), &inputs);
 
-        if self.ball.position.x < self.player1.position.x {
-            self.scored(2);
-        }
-        if self.ball.position.x > self.player2.position.x {
-            self.scored(1);
+            for event in self.game.events() {
+                match event {
+                    GameEvent::WallBounce(bounce) => self.wall_flashes.push(WallFlash {
+                        bounce: *bounce,
+                        remaining: WALL_FLASH_TIME,
+                    }),
+                    GameEvent::GameOver { winner } => println!("winner {winner}"),
+                }
+            }
         }
 
-        if self.score[0] == 10 {
-            self.winner = 1;
-            self.game_over = true;
-        } else if self.score[1] == 10 {
-            self.winner = 2;
-            self.game_over = true;
+        // Effects freeze with the game while it is paused.
+        if self.game.phase == Phase::Paused {
+            return;
         }
 
-        self.time_to_spawn_power_up -= args.dt;
-        if self.time_to_spawn_power_up <= 0.0 {
-            self.spawn_power_up();
-            self.time_to_spawn_power_up = 10.0;
+        for flash in &mut self.wall_flashes {
+            flash.remaining -= args.dt;
         }
+        self.wall_flashes.retain(|flash| flash.remaining > 0.0);
+    }
 
-        let mut collected_indices = Vec::new();
+    fn finished(&self) -> bool {
+        self.game.quit_requested
+            || self.playback_finished
+            || self.sources.iter().any(|source| source.disconnected())
+    }
 
-        for (i, powerup) in self.active_powerups.iter().enumerate() {
-            if powerup.collectable() && powerup.collided(&self.ball) {
-                collected_indices.push(i);
+    fn key_press(&mut self, key: Key) {
+        if let Some(menu) = &mut self.bindings_menu {
+            if let MenuOutcome::Close = menu.key_press(key, &mut self.bindings) {
+                if menu.changed
+                    && let Err(err) = Config::save_keys(&self.config_path, &self.bindings)
+                {
+                    eprintln!("pong: {err}");
+                }
+                self.bindings_menu = None;
             }
+            return;
         }
 
-        for i in collected_indices.into_iter().rev() {
-            self.active_powerups[i].collect(
-                self.ball.last_hit,
-                &mut self.player1,
-                &mut self.player2,
-            );
-            self.active_powerups.remove(i);
+        if key == MENU_KEY && self.game.phase == Phase::Title && self.playback.is_none() {
+            self.bindings_menu = Some(BindingsMenu::default());
+            self.pressed_keys.clear();
+            return;
         }
-    }
 
-    fn key_press(&mut self, key: Key) {
         self.pressed_keys.insert(key);
     }
 
     fn key_release(&mut self, key: Key) {
         self.pressed_keys.remove(&key);
     }
+}
 
-    fn scored(&mut self, scoring_player: usize) {
-        self.ball.speed = 0.0;
-        self.score[scoring_player - 1] += 1;
-        self.kick_off = 3 - scoring_player;
-
-        println!("{}", self.score[0]);
-
-        self.player1.reset(1);
-        self.player2.reset(2);
-
-        let (ball_x, angle) = if self.kick_off == 1 {
-            (self.player1.position.x + self.player1.width + 20.0, 0.0)
-        } else {
-            (self.player2.position.x - 20.0, PI)
-        };
+fn main() {
+    let options = match Options::from_args() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("pong: {err}");
+            std::process::exit(2);
+        }
+    };
 
-        self.ball.position.x = ball_x;
-        self.ball.position.y = HEIGHT / 2.0;
-        self.ball.angle = angle;
+    let replay = match &options.replay {
+        Some(path) => match Replay::load(path) {
+            Ok(replay) => Some(replay),
+            Err(err) => {
+                eprintln!("pong: {}: {err}", path.display());
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
 
-        self.active_powerups.clear();
+    // Joining another machine's match means playing with its settings.
+    let mut peer = None;
+    let mut hosted = None;
+    for (i, spec) in options.sources.iter().enumerate() {
+        if let SourceSpec::Connect(address) = spec {
+            match PeerSource::connect(address, i + 1) {
+                Ok((source, seed, config)) => {
+                    peer = Some(source);
+                    hosted = Some((seed, config));
+                }
+                Err(err) => fail(format!("{address}: {err}")),
+            }
+        }
     }
 
-    fn spawn_power_up(&mut self) {
-        let mut rng = rand::rng();
-        let types = [
-            PowerUpType::Enlarge,
-            PowerUpType::Shrink,
-            PowerUpType::SpeedUp,
-            PowerUpType::SlowDown,
-        ];
-
-        let rnd_type = types.choose(&mut rng).unwrap();
-
-        let spawn_x = rng.random_range(SPRITE_SPAWN_MARGIN..WIDTH - SPRITE_SPAWN_MARGIN);
-        let spawn_y = rng.random_range(50.0..HEIGHT - 50.0);
-
-        match rnd_type {
-            PowerUpType::Enlarge => self
-                .active_powerups
-                .push(Box::new(Enlarge::new(spawn_x, spawn_y))),
-            PowerUpType::Shrink => self
-                .active_powerups
-                .push(Box::new(Shrink::new(spawn_x, spawn_y))),
-            PowerUpType::SpeedUp => self
-                .active_powerups
-                .push(Box::new(SpeedUp::new(spawn_x, spawn_y))),
-            PowerUpType::SlowDown => self
-                .active_powerups
-                .push(Box::new(SlowDown::new(spawn_x, spawn_y))),
+    let (seed, config) = match (&replay, hosted) {
+        (Some(replay), _) => (replay.seed, replay.config.clone()),
+        (None, Some(hosted)) => hosted,
+        (None, None) => (
+            options.config.seed.unwrap_or_else(rand::random),
+            options.config.clone(),
+        ),
+    };
+    let tick_rate = config.tick_rate;
+    println!("seed {seed}");
+
+    for (i, spec) in options.sources.iter().enumerate() {
+        if let SourceSpec::Listen(address) = spec {
+            let player = i + 1;
+            let listener = TcpListener::bind(address)
+                .and_then(|listener| {
+                    println!("waiting for player {player} on {}", listener.local_addr()?);
+                    Ok(listener)
+                })
+                .unwrap_or_else(|err| fail(format!("{address}: {err}")));
+
+            match PeerSource::accept(&listener, player, seed, &config) {
+                Ok(source) => {
+                    if let Ok(addr) = source.peer_addr() {
+                        println!("player {player} connected from {addr}");
+                    }
+                    peer = Some(source);
+                }
+                Err(err) => fail(format!("{address}: {err}")),
+            }
         }
     }
-}
 
-fn main() {
+    let players = config.rules.players;
+    let sources = (1..=players)
+        .map(|player| {
+            options.sources[player - 1]
+                .build(player, seed, &options.config, &mut peer)
+                .unwrap_or_else(|err| fail(err))
+        })
+        .collect();
+
+    if options.headless {
+        run_headless(
+            GameState::new(seed, config.clone()),
+            replay.map(ReplayPlayer::new),
+            sources,
+        );
+        return;
+    }
+
     let opengl = OpenGL::V3_2;
     let mut window: Window = WindowSettings::new("Pong", [WIDTH, HEIGHT])
         .graphics_api(opengl)
@@ -258,51 +324,46 @@ fn main() {
         .build()
         .unwrap();
 
-    let player1 = Player {
-        height: 80.0,
-        width: 16.0,
-        speed: 5.0,
-        position: Position {
-            x: 50.0,
-            y: HEIGHT / 2.0 - 40.0,
-        },
-    };
-
-    let player2 = Player {
-        height: 80.0,
-        width: 16.0,
-        speed: 5.0,
-        position: Position {
-            x: WIDTH - 50.0 - 16.0,
-            y: HEIGHT / 2.0 - 40.0,
-        },
-    };
+    // Relative mode reads mouse motion without the cursor leaving the window.
+    if options.sources[..players].contains(&SourceSpec::Mouse(MouseMode::Relative)) {
+        window.set_capture_cursor(true);
+    }
 
-    let ball = Ball {
-        radius: 10.0,
-        speed: 3.0,
-        position: Position {
-            x: player1.position.x + player1.width + 20.0,
-            y: HEIGHT / 2.0,
-        },
-        angle: 0.0, //radians
-        last_hit: 1,
+    // Controllers only join players whose source takes one.
+    let gamepads = Gamepads::new(
+        options.config.gamepad.clone(),
+        std::array::from_fn(|i| i < players && options.sources[i].takes_gamepad()),
+    );
+
+    let gilrs = match Gilrs::new() {
+        Ok(gilrs) => Some(gilrs),
+        Err(err) => {
+            eprintln!("pong: controllers unavailable: {err}");
+            None
+        }
     };
 
     let mut app = App {
         gl: GlGraphics::new(opengl),
         number_renderer: NumberRenderer::new(),
-        powerup_sprites: PowerUpSprites::new(),
-        active_powerups: Vec::new(),
+        powerup_sprites: PowerUpSprites::new(&config.powerups.definitions)
+            .unwrap_or_else(|err| fail(err)),
         pressed_keys: HashSet::new(),
-        player1,
-        player2,
-        kick_off: 1,
-        ball,
-        game_over: false,
-        score: [0, 0],
-        winner: 0,
-        time_to_spawn_power_up: 10.0,
+        timestep: FixedTimestep::new(tick_rate),
+        game: GameState::new(seed, config.clone()),
+        recording: options
+            .record
+            .as_ref()
+            .map(|_| Replay::new(seed, config.clone())),
+        playback: replay.map(ReplayPlayer::new),
+        playback_finished: false,
+        sources,
+        wall_flashes: Vec::new(),
+        bindings: options.config.keys.clone(),
+        gamepads,
+        gilrs,
+        bindings_menu: None,
+        config_path: options.config_path.clone(),
     };
 
     let mut events = Events::new(EventSettings::new());
@@ -314,9 +375,7 @@ fn main() {
 
         if let Some(args) = e.update_args() {
             app.update(&args);
-
-            if app.game_over {
-                print!("{:?}", app.winner);
+            if app.finished() {
                 WindowTrait::set_should_close(&mut window, true);
             }
         }
@@ -328,5 +387,84 @@ fn main() {
         if let Some(Button::Keyboard(key)) = e.release_args() {
             app.key_release(key);
         }
+
+        for source in &mut app.sources {
+            source.event(&e);
+        }
+    }
+
+    if let (Some(path), Some(recording)) = (&options.record, &app.recording)
+        && let Err(err) = recording.save(path)
+    {
+        eprintln!("pong: {}: {err}", path.display());
+    }
+}
+
+/// Plays a replay, or a match between sources that need no window, to the
+/// end and prints the final score, or the lives left in a four-player match.
+fn run_headless(
+    mut game: GameState,
+    mut playback: Option<ReplayPlayer>,
+    mut sources: Vec<Box<dyn InputSource>>,
+) {
+    let dt = 1.0 / game.config.tick_rate;
+    let mut elapsed = 0.0;
+
+    let keys = HashSet::new();
+    let bindings = KeyBindings::default();
+    let gamepads = Gamepads::new(game.config.gamepad.clone(), [false; MAX_PLAYERS]);
+
+    // Start the match on the first tick the way a player would, so a
+    // machine on the other end of the network starts on the same tick.
+    // A replay starts it with its own recorded inputs.
+    let mut start = true;
+
+    while !game.is_over() && elapsed < HEADLESS_TIME_LIMIT {
+        let inputs = match &mut playback {
+            Some(playback) => match playback.next_inputs() {
+                Some(inputs) => inputs,
+                None => break,
+            },
+            None => {
+                let context = TickContext {
+                    game: &game,
+                    dt,
+                    keys: &keys,
+                    bindings: &bindings,
+                    gamepads: &gamepads,
+                };
+                let mut inputs = Inputs {
+                    start,
+                    ..Inputs::default()
+                };
+                start = false;
+                update_sources(&mut sources, &context, &mut inputs);
+                if sources.iter().any(|source| source.disconnected()) {
+                    break;
+                }
+                inputs
+            }
+        };
+
+        game.step(dt, &inputs);
+        elapsed += dt;
     }
+
+    if game.config.rules.is_four_player() {
+        let lives: Vec<String> = game.lives.iter().map(u32::to_string).collect();
+        println!("lives {}", lives.join(" "));
+    } else {
+        println!("score {} {}", game.score[0], game.score[1]);
+    }
+    if game.config.rules.best_of > 1 {
+        println!("games {} {}", game.games_won[0], game.games_won[1]);
+    }
+    if game.is_over() {
+        println!("winner {}", game.winner);
+    }
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("pong: {message}");
+    std::process::exit(1);
 }
@@ -0,0 +1,79 @@
+use piston::Key;
+
+use crate::input::{Action, KeyBindings, MENU_KEY, key_name};
+
+/// The screen for changing key bindings, opened from the title screen.
+///
+/// It reads raw key presses rather than `Inputs`, so the keys it uses to
+/// get around can't be rebound away.
+#[derive(Default)]
+pub struct BindingsMenu {
+    /// Index into `Action::ALL`.
+    pub selected: usize,
+    /// Whether the next key pressed is bound to the selected action.
+    pub waiting: bool,
+    /// Result of the last change, shown under the list.
+    pub notice: Option<String>,
+    /// Whether any key was changed, so the bindings need saving.
+    pub changed: bool,
+}
+
+pub enum MenuOutcome {
+    Stay,
+    Close,
+}
+
+impl BindingsMenu {
+    pub fn key_press(&mut self, key: Key, bindings: &mut KeyBindings) -> MenuOutcome {
+        if self.waiting {
+            self.waiting = false;
+            self.notice = Some(self.rebind(key, bindings));
+            return MenuOutcome::Stay;
+        }
+
+        match key {
+            Key::Up => {
+                self.selected = self
+                    .selected
+                    .checked_sub(1)
+                    .unwrap_or(Action::ALL.len() - 1)
+            }
+            Key::Down => self.selected = (self.selected + 1) % Action::ALL.len(),
+            Key::Return => {
+                self.waiting = true;
+                self.notice = None;
+            }
+            Key::Delete | Key::Backspace => {
+                *bindings = KeyBindings::default();
+                self.changed = true;
+                self.notice = Some("DEFAULT KEYS RESTORED".to_string());
+            }
+            MENU_KEY => return MenuOutcome::Close,
+            _ => {}
+        }
+        MenuOutcome::Stay
+    }
+
+    pub fn selected_action(&self) -> Action {
+        Action::ALL[self.selected]
+    }
+
+    fn rebind(&mut self, key: Key, bindings: &mut KeyBindings) -> String {
+        let action = self.selected_action();
+        let name = key_name(key);
+
+        if key == MENU_KEY {
+            return format!("{name} IS KEPT FOR THIS MENU");
+        }
+
+        self.changed = true;
+        match bindings.bind(action, key) {
+            Some(other) => format!(
+                "{name} WAS {}: SWAPPED, IT NOW USES {}",
+                other.label(),
+                key_name(bindings.key(other))
+            ),
+            None => format!("{} IS NOW {name}", action.label()),
+        }
+    }
+}
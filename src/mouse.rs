@@ -0,0 +1,118 @@
+use piston::{Button, Event, MouseButton, MouseCursorEvent, MouseRelativeEvent};
+use piston::{PressEvent, ReleaseEvent};
+use serde::{Deserialize, Serialize};
+
+use crate::Position;
+use crate::constants::{HEIGHT, WIDTH};
+use crate::input::{Inputs, PaddleInput};
+use crate::source::{InputSource, TickContext};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MouseConfig {
+    /// Paddle target movement per pixel of mouse movement in relative mode.
+    pub sensitivity: f64,
+}
+
+impl Default for MouseConfig {
+    fn default() -> Self {
+        MouseConfig { sensitivity: 1.0 }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MouseMode {
+    /// The paddle heads for the cursor's height, or the cursor's place
+    /// across the field for a paddle on the top or bottom.
+    Absolute,
+    /// Moving the mouse moves the paddle's target by the same amount,
+    /// wherever the cursor is. The cursor is captured by the window.
+    Relative,
+}
+
+impl MouseMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "absolute" => Some(MouseMode::Absolute),
+            "relative" => Some(MouseMode::Relative),
+            _ => None,
+        }
+    }
+}
+
+/// Steers one paddle towards where the mouse points. The paddle still moves
+/// under its own speed and acceleration, so it follows rather than jumps.
+pub struct MouseControl {
+    player: usize,
+    mode: MouseMode,
+    /// Scales relative mouse motion.
+    sensitivity: f64,
+    /// Point the paddle heads for; only the coordinate along its side
+    /// counts.
+    target: Position,
+    serve: bool,
+}
+
+impl MouseControl {
+    pub fn new(player: usize, mode: MouseMode, config: &MouseConfig) -> Self {
+        MouseControl {
+            player,
+            mode,
+            sensitivity: config.sensitivity,
+            target: Position {
+                x: WIDTH / 2.0,
+                y: HEIGHT / 2.0,
+            },
+            serve: false,
+        }
+    }
+
+    /// The cursor moved to `position` in the window.
+    fn cursor(&mut self, position: [f64; 2]) {
+        if self.mode == MouseMode::Absolute {
+            self.target = Position {
+                x: position[0].clamp(0.0, WIDTH),
+                y: position[1].clamp(0.0, HEIGHT),
+            };
+        }
+    }
+
+    /// The mouse moved by `delta`.
+    fn relative(&mut self, delta: [f64; 2]) {
+        if self.mode == MouseMode::Relative {
+            self.target = Position {
+                x: (self.target.x + delta[0] * self.sensitivity).clamp(0.0, WIDTH),
+                y: (self.target.y + delta[1] * self.sensitivity).clamp(0.0, HEIGHT),
+            };
+        }
+    }
+}
+
+impl InputSource for MouseControl {
+    fn update(&mut self, context: &TickContext, inputs: &mut Inputs) {
+        let paddle = context.game.player(self.player);
+        let (target, _) = paddle.side.along_across(&self.target);
+        let axis = paddle.steer_towards(target, context.dt).clamp(-1.0, 1.0);
+
+        *inputs.paddle_mut(self.player) = PaddleInput {
+            axis,
+            serve: self.serve,
+        };
+    }
+
+    /// Follows the mouse; the left button serves.
+    fn event(&mut self, event: &Event) {
+        if let Some(position) = event.mouse_cursor_args() {
+            self.cursor(position);
+        }
+        if let Some(delta) = event.mouse_relative_args() {
+            self.relative(delta);
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = event.press_args() {
+            self.serve = true;
+        }
+        if let Some(Button::Mouse(MouseButton::Left)) = event.release_args() {
+            self.serve = false;
+        }
+    }
+}
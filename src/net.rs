@@ -0,0 +1,334 @@
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+use crate::config::Config;
+use crate::input::Inputs;
+use crate::source::{InputSource, TickContext};
+
+/// Bump whenever the messages or the meaning of the inputs sent change.
+const PROTOCOL_VERSION: u32 = 1;
+
+/// One line sent between the two machines.
+#[derive(Serialize, Deserialize)]
+enum Message {
+    /// Sent once by the listening side so both run the same match.
+    Hello {
+        version: u32,
+        /// Player the listening side expects the connecting side to play.
+        remote_player: usize,
+        seed: u64,
+        config: Box<Config>,
+    },
+    /// Everything the sender's own sources decided for one tick.
+    Tick(Inputs),
+}
+
+/// A player on another machine, kept in lockstep over TCP.
+///
+/// Both machines run the whole match. Every tick each sends its own inputs
+/// and then waits for the other's, so both step with exactly the same
+/// inputs and stay in sync without sending any game state.
+pub struct PeerSource {
+    player: usize,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    disconnected: bool,
+}
+
+impl PeerSource {
+    /// Waits for the other machine to connect to `listener`, then sends it
+    /// the match settings.
+    pub fn accept(
+        listener: &TcpListener,
+        player: usize,
+        seed: u64,
+        config: &Config,
+    ) -> io::Result<Self> {
+        let (stream, _) = listener.accept()?;
+
+        let mut source = PeerSource::new(stream, player)?;
+        source.send(&Message::Hello {
+            version: PROTOCOL_VERSION,
+            remote_player: player,
+            seed,
+            config: Box::new(config.clone()),
+        })?;
+        Ok(source)
+    }
+
+    /// Connects to a machine listening on `address` and returns the seed
+    /// and config it is playing with.
+    pub fn connect(address: &str, player: usize) -> io::Result<(Self, u64, Config)> {
+        let stream = TcpStream::connect(address)?;
+        let mut source = PeerSource::new(stream, player)?;
+
+        let Message::Hello {
+            version,
+            remote_player,
+            seed,
+            config,
+        } = source.receive()?
+        else {
+            return Err(invalid_data("expected the match settings".to_string()));
+        };
+
+        if version != PROTOCOL_VERSION {
+            return Err(invalid_data(format!(
+                "the other machine runs version {version}, expected {PROTOCOL_VERSION}"
+            )));
+        }
+        if remote_player == player {
+            return Err(invalid_data(format!(
+                "the other machine is waiting for player {player} too"
+            )));
+        }
+
+        let mut config = *config;
+        config
+            .load_power_ups()
+            .and_then(|()| config.validate())
+            .map_err(|err| invalid_data(err.to_string()))?;
+
+        Ok((source, seed, config))
+    }
+
+    /// Address of the other machine.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.writer.peer_addr()
+    }
+
+    fn new(stream: TcpStream, player: usize) -> io::Result<Self> {
+        // Every tick waits on a small message; don't let it sit in a buffer.
+        stream.set_nodelay(true)?;
+
+        Ok(PeerSource {
+            player,
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            disconnected: false,
+        })
+    }
+
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        let line = ron::to_string(message).map_err(|err| invalid_data(err.to_string()))?;
+        writeln!(self.writer, "{line}")
+    }
+
+    fn receive(&mut self) -> io::Result<Message> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        ron::from_str(&line).map_err(|err| invalid_data(err.to_string()))
+    }
+
+    /// Swaps this tick's inputs with the other machine.
+    fn exchange(&mut self, local: &Inputs) -> io::Result<Inputs> {
+        self.send(&Message::Tick(*local))?;
+
+        match self.receive()? {
+            Message::Tick(remote) => Ok(remote),
+            Message::Hello { .. } => Err(invalid_data("unexpected match settings".to_string())),
+        }
+    }
+}
+
+impl InputSource for PeerSource {
+    fn update(&mut self, _context: &TickContext, inputs: &mut Inputs) {
+        if self.disconnected {
+            return;
+        }
+
+        match self.exchange(inputs) {
+            Ok(remote) => {
+                *inputs.paddle_mut(self.player) = remote.paddle(self.player);
+                inputs.merge_buttons(&remote);
+            }
+            Err(err) => {
+                eprintln!("pong: lost player {}: {err}", self.player);
+                self.disconnected = true;
+            }
+        }
+    }
+
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    fn disconnected(&self) -> bool {
+        self.disconnected
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{AiController, Difficulty};
+    use crate::constants::MAX_PLAYERS;
+    use crate::game::{GameState, Phase};
+    use crate::gamepad::Gamepads;
+    use crate::input::KeyBindings;
+    use crate::source::update_sources;
+    use std::collections::HashSet;
+    use std::thread::{self, JoinHandle};
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.load_power_ups().unwrap();
+        config
+    }
+
+    /// A listener on a free port of this machine, and its address.
+    fn local_listener() -> (TcpListener, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        (listener, address)
+    }
+
+    /// Hosts a match on another thread, waiting for `player` to connect.
+    fn host(player: usize, seed: u64, config: Config) -> (JoinHandle<PeerSource>, String) {
+        let (listener, address) = local_listener();
+        let host =
+            thread::spawn(move || PeerSource::accept(&listener, player, seed, &config).unwrap());
+        (host, address)
+    }
+
+    /// Plays `ticks` ticks against `peer`, with an easy AI for `player`.
+    /// The side that `starts` presses start on the first tick, the way
+    /// `--headless` does.
+    fn play(peer: PeerSource, player: usize, seed: u64, config: Config, starts: bool) -> GameState {
+        let dt = 1.0 / config.tick_rate;
+        let keys = HashSet::new();
+        let bindings = KeyBindings::default();
+        let gamepads = Gamepads::new(config.gamepad.clone(), [false; MAX_PLAYERS]);
+        let mut sources: Vec<Box<dyn InputSource>> = vec![
+            Box::new(AiController::new(player, Difficulty::Easy, seed)),
+            Box::new(peer),
+        ];
+
+        let mut game = GameState::new(seed, config);
+        let mut start = starts;
+        for _ in 0..1200 {
+            let context = TickContext {
+                game: &game,
+                dt,
+                keys: &keys,
+                bindings: &bindings,
+                gamepads: &gamepads,
+            };
+            let mut inputs = Inputs {
+                start,
+                ..Inputs::default()
+            };
+            start = false;
+            update_sources(&mut sources, &context, &mut inputs);
+            assert!(!sources[1].disconnected());
+            game.step(dt, &inputs);
+        }
+        game
+    }
+
+    #[test]
+    fn connecting_receives_the_match_settings() {
+        let mut config = config();
+        config.tick_rate = 90.0;
+        let (host, address) = host(2, 7, config);
+
+        let (source, seed, config) = PeerSource::connect(&address, 1).unwrap();
+        host.join().unwrap();
+        assert_eq!(source.player, 1);
+        assert_eq!(seed, 7);
+        assert_eq!(config.tick_rate, 90.0);
+    }
+
+    #[test]
+    fn connecting_to_another_version_fails() {
+        let (listener, address) = local_listener();
+        let config = config();
+        let host = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut source = PeerSource::new(stream, 2).unwrap();
+            source
+                .send(&Message::Hello {
+                    version: PROTOCOL_VERSION + 1,
+                    remote_player: 2,
+                    seed: 7,
+                    config: Box::new(config),
+                })
+                .unwrap();
+        });
+
+        let Err(err) = PeerSource::connect(&address, 1) else {
+            panic!("connected to another version");
+        };
+        host.join().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("version"), "{err}");
+    }
+
+    #[test]
+    fn both_sides_playing_the_same_player_fails() {
+        let (host, address) = host(1, 7, config());
+
+        let Err(err) = PeerSource::connect(&address, 1) else {
+            panic!("both sides play player 1");
+        };
+        host.join().unwrap();
+        assert!(err.to_string().contains("player 1"), "{err}");
+    }
+
+    #[test]
+    fn the_other_side_leaving_disconnects() {
+        let (host, address) = host(2, 7, config());
+        let (mut source, seed, config) = PeerSource::connect(&address, 1).unwrap();
+        drop(host.join().unwrap());
+
+        let game = GameState::new(seed, config);
+        let keys = HashSet::new();
+        let bindings = KeyBindings::default();
+        let gamepads = Gamepads::new(game.config.gamepad.clone(), [false; MAX_PLAYERS]);
+        let context = TickContext {
+            game: &game,
+            dt: 1.0 / game.config.tick_rate,
+            keys: &keys,
+            bindings: &bindings,
+            gamepads: &gamepads,
+        };
+        assert!(!source.disconnected());
+        source.update(&context, &mut Inputs::default());
+        assert!(source.disconnected());
+    }
+
+    #[test]
+    fn both_sides_play_the_same_match() {
+        let seed = 4;
+        let (listener, address) = local_listener();
+        let config = config();
+        let host = thread::spawn(move || {
+            let peer = PeerSource::accept(&listener, 2, seed, &config).unwrap();
+            play(peer, 1, seed, config, true)
+        });
+
+        // Only the host presses start; the connecting side starts with it.
+        let (peer, seed, config) = PeerSource::connect(&address, 1).unwrap();
+        let joined = play(peer, 2, seed, config, false);
+        let hosted = host.join().unwrap();
+
+        assert_ne!(hosted.phase, Phase::Title);
+        assert_eq!(joined.phase, hosted.phase);
+        assert_eq!(joined.score, hosted.score);
+        assert_eq!(joined.game_time, hosted.game_time);
+        for (a, b) in joined.players.iter().zip(&hosted.players) {
+            assert_eq!((a.position.x, a.position.y), (b.position.x, b.position.y));
+        }
+        for (a, b) in joined.balls.iter().zip(&hosted.balls) {
+            assert_eq!((a.position.x, a.position.y), (b.position.x, b.position.y));
+            assert_eq!(a.angle, b.angle);
+        }
+    }
+}
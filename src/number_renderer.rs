@@ -22,6 +22,7 @@ impl NumberRenderer {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn render(
         &self,
         number: u32,
@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, DEFAULT_CONFIG_PATH};
+use crate::constants::MAX_PLAYERS;
+use crate::rules::{MultiballScoring, Tiebreak};
+use crate::source::SourceSpec;
+
+/// Command line options. Gameplay options override the config file.
+pub struct Options {
+    pub config: Config,
+    /// File the config was read from, and where rebound keys are saved.
+    pub config_path: PathBuf,
+    /// Write the match inputs to this replay file on exit.
+    pub record: Option<PathBuf>,
+    /// Play back this replay file instead of reading the keyboard.
+    pub replay: Option<PathBuf>,
+    /// Run the replay without opening a window and print the result.
+    pub headless: bool,
+    /// Who controls each player. Only the first `rules.players` are used.
+    pub sources: [SourceSpec; MAX_PLAYERS],
+}
+
+impl Options {
+    pub fn from_args() -> Result<Self, String> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let args: Vec<String> = args.into_iter().collect();
+
+        // The config file is loaded first so the other options can override it.
+        let config_path = match args.iter().position(|arg| arg == "--config") {
+            Some(i) => Some(
+                args.get(i + 1)
+                    .ok_or_else(|| "missing value for '--config'".to_string())?,
+            ),
+            None => None,
+        };
+        let config =
+            Config::load_or_default(config_path.map(Path::new)).map_err(|err| err.to_string())?;
+
+        let mut options = Options {
+            config,
+            config_path: config_path.map_or(DEFAULT_CONFIG_PATH.into(), PathBuf::from),
+            record: None,
+            replay: None,
+            headless: false,
+            sources: Default::default(),
+        };
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => {
+                    next_value(&mut args, &arg)?;
+                }
+                "--tick-rate" => {
+                    let value = next_value(&mut args, &arg)?;
+                    options.config.tick_rate = value
+                        .parse()
+                        .map_err(|_| format!("invalid tick rate '{value}'"))?;
+                }
+                "--seed" => {
+                    let value = next_value(&mut args, &arg)?;
+                    options.config.seed = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid seed '{value}'"))?,
+                    );
+                }
+                "--record" => options.record = Some(next_value(&mut args, &arg)?.into()),
+                "--replay" => options.replay = Some(next_value(&mut args, &arg)?.into()),
+                "--headless" => options.headless = true,
+                "--p1" | "--p2" | "--p3" | "--p4" => {
+                    let value = next_value(&mut args, &arg)?;
+                    options.sources[player_index(&arg[2..])] = SourceSpec::parse(&value)?;
+                }
+                // Shorthands for `--p1 ai:<level>` and `--p1 mouse:<mode>`.
+                "--p1-ai" | "--p2-ai" | "--p3-ai" | "--p4-ai" | "--p1-mouse" | "--p2-mouse"
+                | "--p3-mouse" | "--p4-mouse" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let (player, kind) = arg[2..].split_once('-').unwrap();
+                    options.sources[player_index(player)] =
+                        SourceSpec::parse(&format!("{kind}:{value}"))?;
+                }
+                "--players" => {
+                    let value = next_value(&mut args, &arg)?;
+                    options.config.rules.players = value
+                        .parse()
+                        .map_err(|_| format!("invalid number of players '{value}'"))?;
+                }
+                "--lives" => {
+                    let value = next_value(&mut args, &arg)?;
+                    options.config.rules.lives = value
+                        .parse()
+                        .map_err(|_| format!("invalid number of lives '{value}'"))?;
+                }
+                "--target-score" => {
+                    let value = next_value(&mut args, &arg)?;
+                    options.config.rules.target_score = value
+                        .parse()
+                        .map_err(|_| format!("invalid target score '{value}'"))?;
+                }
+                "--win-by-two" => options.config.rules.win_by_two = true,
+                "--time-limit" => {
+                    let value = next_value(&mut args, &arg)?;
+                    options.config.rules.time_limit = Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("invalid time limit '{value}'"))?,
+                    );
+                }
+                "--tiebreak" => {
+                    let value = next_value(&mut args, &arg)?;
+                    options.config.rules.tiebreak = Tiebreak::parse(&value)
+                        .ok_or_else(|| format!("unknown tiebreak '{value}'"))?;
+                }
+                "--best-of" => {
+                    let value = next_value(&mut args, &arg)?;
+                    options.config.rules.best_of = value
+                        .parse()
+                        .map_err(|_| format!("invalid series length '{value}'"))?;
+                }
+                "--multiball" => {
+                    let value = next_value(&mut args, &arg)?;
+                    options.config.rules.multiball = MultiballScoring::parse(&value)
+                        .ok_or_else(|| format!("unknown multi-ball scoring '{value}'"))?;
+                }
+                _ => return Err(format!("unknown option '{arg}'")),
+            }
+        }
+
+        options.config.validate().map_err(|err| err.to_string())?;
+
+        let players = options.config.rules.players;
+        if let Some(extra) = options.sources[players..]
+            .iter()
+            .position(|source| *source != SourceSpec::Local)
+        {
+            return Err(format!(
+                "player {} needs '--players 4'",
+                players + extra + 1
+            ));
+        }
+
+        let sources = &options.sources[..players];
+        let all_headless = sources.iter().all(SourceSpec::is_headless);
+        if options.headless && options.replay.is_none() && !all_headless {
+            return Err(
+                "'--headless' needs a '--replay' file, or every paddle played by the AI, a \
+                 replay or the other machine"
+                    .to_string(),
+            );
+        }
+        let mice = sources
+            .iter()
+            .filter(|source| matches!(source, SourceSpec::Mouse(_)))
+            .count();
+        if mice > 1 {
+            return Err("only one player can use the mouse".to_string());
+        }
+        let peers = sources.iter().filter(|source| source.is_peer()).count();
+        if peers > 1 {
+            return Err("only one player can be on another machine".to_string());
+        }
+        if options.replay.is_some() && peers > 0 {
+            return Err("'--replay' can't be combined with another machine".to_string());
+        }
+        if options.record.is_some() && options.replay.is_some() {
+            return Err("'--record' and '--replay' can't be combined".to_string());
+        }
+
+        Ok(options)
+    }
+}
+
+/// Index into `Options::sources` for a player named like `p1`.
+fn player_index(player: &str) -> usize {
+    player[1..].parse::<usize>().unwrap() - 1
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>, option: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("missing value for '{option}'"))
+}
@@ -1,69 +1,300 @@
+use std::f64::consts::PI;
+
 use crate::Ball;
 use crate::Position;
+use crate::collision::{Contact, Hit, Rect, circle_rect_contact, sweep_circle_rect};
+use crate::config::PaddleConfig;
 use crate::constants::HEIGHT;
 use crate::constants::WIDTH;
+use crate::input::PaddleInput;
 use crate::object::Object;
+use crate::stat::Stat;
+
+/// Edge of the field a paddle guards. Left and right paddles move up and
+/// down; top and bottom paddles move left and right.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Side {
+    /// Sides taken by each player in a match of `players`, in player order.
+    pub fn for_players(players: usize) -> &'static [Side] {
+        if players == 4 {
+            &[Side::Left, Side::Right, Side::Top, Side::Bottom]
+        } else {
+            &[Side::Left, Side::Right]
+        }
+    }
+
+    /// Whether a paddle on this side moves up and down.
+    pub fn is_vertical(self) -> bool {
+        matches!(self, Side::Left | Side::Right)
+    }
+
+    /// Unit vector from this side into the field.
+    pub fn normal(self) -> [f64; 2] {
+        match self {
+            Side::Left => [1.0, 0.0],
+            Side::Right => [-1.0, 0.0],
+            Side::Top => [0.0, 1.0],
+            Side::Bottom => [0.0, -1.0],
+        }
+    }
+
+    /// Angle of `normal`, in the ball's convention where positive is up.
+    pub fn normal_angle(self) -> f64 {
+        match self {
+            Side::Left => 0.0,
+            Side::Right => PI,
+            Side::Top => -PI / 2.0,
+            Side::Bottom => PI / 2.0,
+        }
+    }
+
+    /// Splits a point into its coordinate along this side and the one
+    /// across it.
+    pub fn along_across(self, position: &Position) -> (f64, f64) {
+        if self.is_vertical() {
+            (position.y, position.x)
+        } else {
+            (position.x, position.y)
+        }
+    }
+
+    /// The sides at the low and high ends of this one: top then bottom for
+    /// the left and right, left then right for the top and bottom.
+    pub fn ends(self) -> [Side; 2] {
+        if self.is_vertical() {
+            [Side::Top, Side::Bottom]
+        } else {
+            [Side::Left, Side::Right]
+        }
+    }
+
+    /// Length of this side of the field.
+    pub fn extent(self) -> f64 {
+        if self.is_vertical() { HEIGHT } else { WIDTH }
+    }
+}
 
 pub struct Player {
-    pub height: f64,
-    pub width: f64,
-    pub speed: f64,
+    pub side: Side,
+    /// Size along its side of the field.
+    pub length: Stat,
+    /// Size across its side of the field.
+    pub thickness: f64,
+    /// Top speed.
+    pub speed: Stat,
+    pub acceleration: f64,
+    pub deceleration: f64,
+    /// Top-left corner.
     pub position: Position,
+    /// Current speed along its side in pixels per second; positive is down
+    /// or to the right.
+    pub velocity: f64,
 }
 
 impl Player {
-    pub fn collided(&self, ball: &Ball) -> bool {
-        let p_lx = self.position.x;
-        let p_rx = self.position.x + self.width;
-        let p_uy = self.position.y;
-        let p_dy = self.position.y + self.height;
-        let b_lx = ball.position.x - ball.radius;
-        let b_rx = ball.position.x + ball.radius;
-        let b_y = ball.position.y;
+    /// Effective length, with effects applied.
+    pub fn length(&self) -> f64 {
+        self.length.value()
+    }
+
+    /// Effective speed, with effects applied.
+    pub fn speed(&self) -> f64 {
+        self.speed.value()
+    }
+
+    pub fn contact(&self, ball: &Ball) -> Option<Contact> {
+        circle_rect_contact(&ball.position, ball.radius(), &self.rect())
+    }
+
+    /// Whether `normal` belongs to the front face rather than an edge or
+    /// corner of the paddle.
+    pub fn is_front(&self, normal: [f64; 2]) -> bool {
+        normal == self.side.normal()
+    }
+
+    /// Which way a positive hit or serve aim turns the ball away from
+    /// straight ahead, so that it leaves towards the end of the paddle it
+    /// met (down or right for positive).
+    pub fn turn(&self) -> f64 {
+        match self.side {
+            Side::Left | Side::Bottom => -1.0,
+            Side::Right | Side::Top => 1.0,
+        }
+    }
+
+    /// Direction for a ball leaving the paddle, from -1.0 (steepest up or
+    /// left) to 1.0 (steepest down or right), at most `max_angle` from
+    /// straight ahead.
+    pub fn angle_towards(&self, aim: f64, max_angle: f64) -> f64 {
+        self.side.normal_angle() + self.turn() * max_angle * aim
+    }
+
+    /// Start of the paddle along its side.
+    pub fn along(&self) -> f64 {
+        self.side.along_across(&self.position).0
+    }
+
+    fn set_along(&mut self, along: f64) {
+        if self.side.is_vertical() {
+            self.position.y = along;
+        } else {
+            self.position.x = along;
+        }
+    }
 
-        (p_uy < b_y && b_y < p_dy) && (b_lx < p_rx && b_rx > p_lx)
+    /// Middle of the paddle along its side.
+    pub fn center(&self) -> f64 {
+        self.along() + self.length() / 2.0
+    }
+
+    /// Point `distance` out from the middle of the front face.
+    pub fn in_front(&self, distance: f64) -> Position {
+        let [nx, ny] = self.side.normal();
+        let rect = self.rect();
+        let middle = Position {
+            x: rect.x + rect.width / 2.0,
+            y: rect.y + rect.height / 2.0,
+        };
+        let reach = self.thickness / 2.0 + distance;
+        Position {
+            x: middle.x + nx * reach,
+            y: middle.y + ny * reach,
+        }
+    }
+
+    /// Whether `ball` went past the paddle into the goal behind it.
+    pub fn is_behind(&self, ball: &Ball) -> bool {
+        match self.side {
+            Side::Left => ball.position.x < self.position.x,
+            Side::Right => ball.position.x > self.position.x,
+            Side::Top => ball.position.y < self.position.y,
+            Side::Bottom => ball.position.y > self.position.y,
+        }
+    }
+
+    pub fn rect(&self) -> Rect {
+        let (width, height) = if self.side.is_vertical() {
+            (self.thickness, self.length())
+        } else {
+            (self.length(), self.thickness)
+        };
+        Rect {
+            x: self.position.x,
+            y: self.position.y,
+            width,
+            height,
+        }
+    }
+
+    /// Swept test for a ball about to travel by `motion` this tick.
+    pub fn sweep(&self, ball: &Ball, motion: [f64; 2]) -> Option<Hit> {
+        sweep_circle_rect(&ball.position, motion, ball.radius(), &self.rect())
     }
 
     pub fn collision_point(&self, ball: &Ball) -> f64 {
-        let b_dy = ball.position.y - self.position.y;
-        let b_ry = b_dy / self.height;
+        let (ball_along, _) = self.side.along_across(&ball.position);
+        let b_r = (ball_along - self.along()) / self.length();
 
-        b_ry * 2.0 - 1.0
+        (b_r * 2.0 - 1.0).clamp(-1.0, 1.0)
     }
 
-    pub fn prevent_out_of_bounds(&mut self) {
-        if self.position.y < 0.0 {
-            self.position.y += self.speed;
+    /// Speeds the paddle up or slows it down towards the velocity the input
+    /// asks for, then moves it.
+    pub fn move_by_input(&mut self, input: &PaddleInput, dt: f64) {
+        let wanted = input.axis.clamp(-1.0, 1.0) * self.speed();
+
+        // Speeding up in the direction already moving uses the acceleration;
+        // slowing down or turning round uses the deceleration.
+        let speeding_up = wanted.abs() > self.velocity.abs() && wanted * self.velocity >= 0.0;
+        let rate = if speeding_up {
+            self.acceleration
+        } else {
+            self.deceleration
+        };
+
+        // Moving at the average of the old and new velocity covers the same
+        // distance whatever the tick rate.
+        let before = self.velocity;
+        let change = (wanted - before).clamp(-rate * dt, rate * dt);
+        self.velocity += change;
+        self.set_along(self.along() + (before + self.velocity) / 2.0 * dt);
+    }
+
+    /// Input axis that moves the paddle's centre to `target` along its side,
+    /// letting go early enough to coast to a stop there. Not limited to
+    /// -1.0 to 1.0.
+    pub fn steer_towards(&self, target: f64, dt: f64) -> f64 {
+        let step = self.speed() * dt;
+        if step <= 0.0 {
+            return 0.0;
         }
-        if self.position.y > HEIGHT - self.height {
-            self.position.y -= self.speed;
+
+        let braking_distance = self.velocity * self.velocity.abs() / (2.0 * self.deceleration);
+        (target - self.center() - braking_distance) / step
+    }
+
+    /// Puts the paddle back against the edge of the field if it went past
+    /// it, and stops it there.
+    pub fn prevent_out_of_bounds(&mut self) {
+        let end = (self.side.extent() - self.length()).max(0.0);
+        let along = self.along();
+
+        if along < 0.0 || along > end {
+            self.set_along(along.clamp(0.0, end));
+            self.velocity = 0.0;
         }
     }
 
-    pub fn reset(&mut self, player: usize) {
-        if player == 1 {
-            self.height = 80.0;
-            self.width = 16.0;
-            self.speed = 5.0;
-            self.position.x = 50.0;
-            self.position.y = HEIGHT / 2.0 - 40.0;
+    pub fn new(side: Side, config: &PaddleConfig) -> Self {
+        let mut paddle = Player {
+            side,
+            length: Stat::new(config.height, config.min_height, config.max_height),
+            thickness: config.width,
+            speed: Stat::new(config.speed, config.min_speed, config.max_speed),
+            acceleration: config.acceleration,
+            deceleration: config.deceleration,
+            position: Position { x: 0.0, y: 0.0 },
+            velocity: 0.0,
+        };
+        paddle.reset(config);
+        paddle
+    }
+
+    /// Moves the paddle back to the middle of its side. Size and speed are
+    /// left to the effects in force.
+    pub fn reset(&mut self, config: &PaddleConfig) {
+        self.set_along(self.side.extent() / 2.0 - self.length() / 2.0);
+        self.velocity = 0.0;
+
+        let across = match self.side {
+            Side::Left | Side::Top => config.inset,
+            Side::Right => WIDTH - config.inset - self.thickness,
+            Side::Bottom => HEIGHT - config.inset - self.thickness,
+        };
+        if self.side.is_vertical() {
+            self.position.x = across;
         } else {
-            self.height = 80.0;
-            self.width = 16.0;
-            self.speed = 5.0;
-            self.position.x = WIDTH - 50.0 - 16.0;
-            self.position.y = HEIGHT / 2.0 - 40.0;
+            self.position.y = across;
         }
     }
 }
 
 impl Object for Player {
+    /// Grows or shrinks the paddle around its centre.
     fn resize_by(&mut self, size: f64) {
-        self.height += size;
-        self.position.y -= size / 2.0;
+        let before = self.length();
+        self.length.modify(size);
+        self.set_along(self.along() - (self.length() - before) / 2.0);
     }
 
     fn change_speed_by(&mut self, speed: f64) {
-        self.speed -= speed;
+        self.speed.modify(speed);
     }
 }
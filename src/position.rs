@@ -1,3 +1,4 @@
+#[derive(Clone, Copy)]
 pub struct Position {
     pub x: f64,
     pub y: f64,
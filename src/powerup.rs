@@ -1,274 +1,154 @@
 use opengl_graphics::GlGraphics;
 use opengl_graphics::Texture as GlTexture;
 use piston_window::{DrawState, Filter, Image, TextureSettings};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
 use crate::Ball;
 use crate::Position;
-use crate::object::Object;
+use crate::collision::{Rect, circle_rect_contact, sweep_circle_rect};
+use crate::effect::{Affected, Effect, EffectKind, Target};
 
 const POWERUP_SIZE: f64 = 32.0;
 
-#[derive(Clone, Copy, Eq, PartialEq, Hash)]
-pub enum PowerUpType {
-    Enlarge,
-    Shrink,
-    SpeedUp,
-    SlowDown,
-}
-
-pub trait PowerUp {
-    fn position(&self) -> &Position;
-
-    fn width(&self) -> f64 {
-        POWERUP_SIZE
-    }
-
-    fn height(&self) -> f64 {
-        POWERUP_SIZE
-    }
-
-    fn collectable(&self) -> bool;
-
-    fn set_collectable(&mut self, collectable: bool);
-
-    fn powerup_type(&self) -> PowerUpType;
-
-    fn collided(&self, ball: &Ball) -> bool {
-        let p_lx = self.position().x;
-        let p_rx = self.position().x + self.width();
-        let p_uy = self.position().y;
-        let p_dy = self.position().y + self.height();
-
-        let b_lx = ball.position.x - ball.radius;
-        let b_rx = ball.position.x + ball.radius;
-        let b_y = ball.position.y;
-
-        (p_uy < b_y && b_y < p_dy) && (b_lx < p_rx && b_rx > p_lx)
-    }
-
-    fn collect(&mut self, last_hit: usize, player1: &mut dyn Object, player2: &mut dyn Object);
-}
-
-pub struct Enlarge {
-    position: Position,
-    pub collectable: bool,
-}
-
-impl Enlarge {
-    pub fn new(x: f64, y: f64) -> Self {
-        Enlarge {
-            position: Position { x, y },
-            collectable: true,
+/// One kind of power-up, as read from the definitions file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PowerUpDef {
+    /// Shown next to the effect's timer.
+    pub name: String,
+    pub target: Target,
+    pub effect: EffectKind,
+    /// Change to the target's size or speed; negative values take away.
+    pub magnitude: f64,
+    /// Seconds the effect lasts once collected. Not needed for `Split`.
+    #[serde(default)]
+    pub duration: f64,
+    /// Image drawn on the field.
+    pub sprite: String,
+    /// Relative chance of this power-up being the one that spawns.
+    pub weight: f64,
+}
+
+impl PowerUpDef {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty() {
+            return Err("power-up name must not be empty".to_string());
         }
-    }
-}
-
-impl PowerUp for Enlarge {
-    fn position(&self) -> &Position {
-        &self.position
-    }
-
-    fn collectable(&self) -> bool {
-        self.collectable
-    }
-
-    fn set_collectable(&mut self, collectable: bool) {
-        self.collectable = collectable;
-    }
-
-    fn powerup_type(&self) -> PowerUpType {
-        PowerUpType::Enlarge
-    }
-
-    fn collect(&mut self, last_hit: usize, player1: &mut dyn Object, player2: &mut dyn Object) {
-        self.set_collectable(false);
-        if last_hit == 1 {
-            player1.resize_by(20.0);
-        } else {
-            player2.resize_by(20.0);
+        if !self.magnitude.is_finite() {
+            return Err(format!("{}: magnitude must be a number", self.name));
         }
-    }
-}
-
-pub struct Shrink {
-    position: Position,
-    pub collectable: bool,
-}
-
-impl Shrink {
-    pub fn new(x: f64, y: f64) -> Self {
-        Shrink {
-            position: Position { x, y },
-            collectable: true,
+        if self.effect == EffectKind::Split {
+            if self.target != Target::Ball || self.magnitude < 1.0 {
+                return Err(format!(
+                    "{}: a split must target the ball and add at least 1 ball",
+                    self.name
+                ));
+            }
+        } else if !(self.duration > 0.0 && self.duration.is_finite()) {
+            return Err(format!("{}: duration must be positive", self.name));
         }
-    }
-}
-
-impl PowerUp for Shrink {
-    fn position(&self) -> &Position {
-        &self.position
-    }
-
-    fn collectable(&self) -> bool {
-        self.collectable
-    }
-
-    fn set_collectable(&mut self, collectable: bool) {
-        self.collectable = collectable;
-    }
-
-    fn powerup_type(&self) -> PowerUpType {
-        PowerUpType::Shrink
-    }
-
-    fn collect(&mut self, last_hit: usize, player1: &mut dyn Object, player2: &mut dyn Object) {
-        self.set_collectable(false);
-        if last_hit == 1 {
-            player2.resize_by(-20.0);
-        } else {
-            player1.resize_by(-20.0);
+        if !(self.weight >= 0.0 && self.weight.is_finite()) {
+            return Err(format!("{}: weight must not be negative", self.name));
         }
+        Ok(())
     }
 }
 
-pub struct SpeedUp {
-    position: Position,
-    pub collectable: bool,
+/// A power-up lying on the field.
+pub struct PowerUp {
+    pub position: Position,
+    /// Index of its definition in `PowerUpConfig::definitions`.
+    pub kind: usize,
 }
 
-impl SpeedUp {
-    pub fn new(x: f64, y: f64) -> Self {
-        SpeedUp {
+impl PowerUp {
+    pub fn new(x: f64, y: f64, kind: usize) -> Self {
+        PowerUp {
             position: Position { x, y },
-            collectable: true,
+            kind,
         }
     }
-}
-
-impl PowerUp for SpeedUp {
-    fn position(&self) -> &Position {
-        &self.position
-    }
-
-    fn collectable(&self) -> bool {
-        self.collectable
-    }
 
-    fn set_collectable(&mut self, collectable: bool) {
-        self.collectable = collectable;
+    pub fn width(&self) -> f64 {
+        POWERUP_SIZE
     }
 
-    fn powerup_type(&self) -> PowerUpType {
-        PowerUpType::SpeedUp
+    pub fn height(&self) -> f64 {
+        POWERUP_SIZE
     }
 
-    fn collect(&mut self, last_hit: usize, player1: &mut dyn Object, player2: &mut dyn Object) {
-        self.set_collectable(false);
-        if last_hit == 1 {
-            player1.change_speed_by(2.0);
-        } else {
-            player2.change_speed_by(2.0);
+    pub fn rect(&self) -> Rect {
+        Rect {
+            x: self.position.x,
+            y: self.position.y,
+            width: self.width(),
+            height: self.height(),
         }
     }
-}
-
-pub struct SlowDown {
-    position: Position,
-    pub collectable: bool,
-}
 
-impl SlowDown {
-    pub fn new(x: f64, y: f64) -> Self {
-        SlowDown {
-            position: Position { x, y },
-            collectable: true,
-        }
-    }
-}
-
-impl PowerUp for SlowDown {
-    fn position(&self) -> &Position {
-        &self.position
+    pub fn collided(&self, ball: &Ball) -> bool {
+        circle_rect_contact(&ball.position, ball.radius(), &self.rect()).is_some()
     }
 
-    fn collectable(&self) -> bool {
-        self.collectable
+    /// Whether the ball touches the power-up anywhere on its way from
+    /// `from` along `motion`.
+    pub fn swept(&self, ball: &Ball, from: &Position, motion: [f64; 2]) -> bool {
+        sweep_circle_rect(from, motion, ball.radius(), &self.rect()).is_some()
     }
 
-    fn set_collectable(&mut self, collectable: bool) {
-        self.collectable = collectable;
-    }
-
-    fn powerup_type(&self) -> PowerUpType {
-        PowerUpType::SpeedUp
-    }
+    /// Effect of collecting the power-up. `collector` is the player who last
+    /// touched the ball, and `opponent` the one an `Opponent` effect lands
+    /// on.
+    pub fn collect(&self, def: &PowerUpDef, collector: usize, opponent: usize) -> Effect {
+        let target = match def.target {
+            Target::Collector => Affected::Player(collector),
+            Target::Opponent => Affected::Player(opponent),
+            Target::Ball => Affected::Ball,
+        };
 
-    fn collect(&mut self, last_hit: usize, player1: &mut dyn Object, player2: &mut dyn Object) {
-        self.set_collectable(false);
-        if last_hit == 1 {
-            player2.change_speed_by(-2.0);
-        } else {
-            player1.change_speed_by(-2.0);
+        Effect {
+            kind: def.effect,
+            target,
+            amount: def.magnitude,
         }
     }
 }
 
+/// Textures for each power-up definition, in the same order.
 pub struct PowerUpSprites {
-    sprites: HashMap<PowerUpType, GlTexture>,
-}
-
-impl Default for PowerUpSprites {
-    fn default() -> Self {
-        Self::new()
-    }
+    sprites: Vec<GlTexture>,
 }
 
 impl PowerUpSprites {
-    pub fn new() -> Self {
+    /// Loads every definition's sprite, failing on the first that can't be
+    /// read.
+    pub fn new(definitions: &[PowerUpDef]) -> Result<Self, String> {
         let texture_settings = TextureSettings::new()
             .filter(Filter::Nearest)
             .mipmap(Filter::Nearest);
 
-        let mut sprites = HashMap::new();
-
-        if let Ok(texture) =
-            GlTexture::from_path(Path::new("assets/enlarge.png"), &texture_settings)
-        {
-            sprites.insert(PowerUpType::Enlarge, texture);
-        }
-
-        if let Ok(texture) = GlTexture::from_path(Path::new("assets/shrink.png"), &texture_settings)
-        {
-            sprites.insert(PowerUpType::Shrink, texture);
-        }
-
-        if let Ok(texture) =
-            GlTexture::from_path(Path::new("assets/speed_up.png"), &texture_settings)
-        {
-            sprites.insert(PowerUpType::SpeedUp, texture);
-        }
-
-        if let Ok(texture) =
-            GlTexture::from_path(Path::new("assets/slow_down.png"), &texture_settings)
-        {
-            sprites.insert(PowerUpType::SlowDown, texture);
-        }
+        let sprites = definitions
+            .iter()
+            .map(|def| {
+                GlTexture::from_path(Path::new(&def.sprite), &texture_settings)
+                    .map_err(|err| format!("{}: {err}", def.sprite))
+            })
+            .collect::<Result<_, _>>()?;
 
-        PowerUpSprites { sprites }
+        Ok(PowerUpSprites { sprites })
     }
 
-    pub fn get(&self, powerup_type: &PowerUpType) -> Option<&GlTexture> {
-        self.sprites.get(powerup_type)
+    pub fn get(&self, kind: usize) -> Option<&GlTexture> {
+        self.sprites.get(kind)
     }
 
-    pub fn render(&self, powerup: &dyn PowerUp, c: &graphics::Context, gl: &mut GlGraphics) {
-        if let Some(sprite) = self.get(&powerup.powerup_type()) {
+    pub fn render(&self, powerup: &PowerUp, c: &graphics::Context, gl: &mut GlGraphics) {
+        if let Some(sprite) = self.get(powerup.kind) {
             Image::new()
                 .rect([
-                    powerup.position().x,
-                    powerup.position().y,
+                    powerup.position.x,
+                    powerup.position.y,
                     powerup.width(),
                     powerup.height(),
                 ])
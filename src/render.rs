@@ -0,0 +1,407 @@
+use graphics::*;
+use opengl_graphics::GlGraphics;
+
+use crate::Position;
+use crate::ball::WallBounce;
+use crate::constants::{HEIGHT, WIDTH};
+use crate::effect::Affected;
+use crate::game::{GameState, Phase};
+use crate::input::{Action, KeyBindings, MENU_KEY, key_name};
+use crate::menu::BindingsMenu;
+use crate::number_renderer::NumberRenderer;
+use crate::player::Side;
+use crate::powerup::PowerUpSprites;
+use crate::rules::format_clock;
+use crate::text::{Align, BitmapText};
+
+const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+const WHITE: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const GREY: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+/// How long the wall lights up where the ball bounced, in seconds.
+pub const WALL_FLASH_TIME: f64 = 0.15;
+
+pub struct WallFlash {
+    pub bounce: WallBounce,
+    pub remaining: f64,
+}
+
+/// Everything needed to draw one frame.
+pub struct Scene<'a> {
+    pub game: &'a GameState,
+    pub number_renderer: &'a NumberRenderer,
+    pub powerup_sprites: &'a PowerUpSprites,
+    pub wall_flashes: &'a [WallFlash],
+    pub keys: &'a KeyBindings,
+    /// Drawn instead of the game while open.
+    pub bindings_menu: Option<&'a BindingsMenu>,
+}
+
+impl Scene<'_> {
+    pub fn draw(&self, c: &Context, gl: &mut GlGraphics) {
+        clear(WHITE, gl);
+
+        if let Some(menu) = self.bindings_menu {
+            self.bindings(menu, c, gl);
+            return;
+        }
+
+        match self.game.phase {
+            Phase::Title => self.title(c, gl),
+            Phase::Serve => {
+                self.field(c, gl);
+                self.serve(c, gl);
+            }
+            Phase::Rally => self.field(c, gl),
+            Phase::PointScored { scorer, game_won } => {
+                self.field(c, gl);
+                self.point_scored(scorer, game_won, c, gl);
+            }
+            Phase::LifeLost { player, eliminated } => {
+                self.field(c, gl);
+                self.life_lost(player, eliminated, c, gl);
+            }
+            Phase::GameOver => {
+                self.field(c, gl);
+                self.game_over(c, gl);
+            }
+            Phase::Paused => {
+                self.field(c, gl);
+                self.paused(c, gl);
+            }
+        }
+    }
+
+    fn title(&self, c: &Context, gl: &mut GlGraphics) {
+        let centered = |color, pixel| BitmapText::new(color, pixel).align(Align::Center);
+
+        centered(BLACK, 16.0).draw("PONG", [WIDTH / 2.0, 200.0], c, gl);
+        let start = format!("PRESS {} TO START", self.key(Action::Start));
+        centered(BLACK, 3.0).draw(&start, [WIDTH / 2.0, 420.0], c, gl);
+        let mut controls: Vec<String> = (1..=self.game.players.len())
+            .map(|player| {
+                let [back, forward, serve] = Action::paddle_actions(player).map(|a| self.key(a));
+                format!("PLAYER {player}: {back} / {forward} TO MOVE, {serve} TO SERVE")
+            })
+            .collect();
+        controls.push(format!(
+            "{}: PAUSE    {}: KEYS",
+            self.key(Action::Pause),
+            key_name(MENU_KEY)
+        ));
+        controls.push("CONTROLLERS: PRESS ANY BUTTON TO JOIN".to_string());
+        for (i, line) in controls.iter().enumerate() {
+            centered(GREY, 2.0).draw(line, [WIDTH / 2.0, 480.0 + 24.0 * i as f64], c, gl);
+        }
+        self.rules_line(c, gl);
+    }
+
+    /// The playing field: paddles, ball, scores, power-ups and effects.
+    fn field(&self, c: &Context, gl: &mut GlGraphics) {
+        let game = self.game;
+
+        for ball in &game.balls {
+            let circle = ellipse::circle(0.0, 0.0, ball.radius());
+            let ball_transform = c.transform.trans(ball.position.x, ball.position.y);
+            ellipse(BLACK, circle, ball_transform, gl);
+        }
+        for player in game.players_in() {
+            let rect = game.player(player).rect();
+            rectangle(
+                BLACK,
+                [rect.x, rect.y, rect.width, rect.height],
+                c.transform,
+                gl,
+            );
+        }
+
+        if game.config.rules.is_four_player() {
+            self.lives(c, gl);
+        } else {
+            self.scores(c, gl);
+        }
+        self.effect_timers(c, gl);
+
+        for powerup in &game.active_powerups {
+            self.powerup_sprites.render(powerup, c, gl);
+        }
+
+        for flash in self.wall_flashes {
+            let alpha = (flash.remaining / WALL_FLASH_TIME) as f32;
+            let Position { x, y } = flash.bounce.position;
+            let rect = match flash.bounce.wall {
+                Side::Top => [x - 30.0, 0.0, 60.0, 4.0],
+                Side::Bottom => [x - 30.0, HEIGHT - 4.0, 60.0, 4.0],
+                Side::Left => [0.0, y - 30.0, 4.0, 60.0],
+                Side::Right => [WIDTH - 4.0, y - 30.0, 4.0, 60.0],
+            };
+            rectangle([0.0, 0.0, 0.0, alpha], rect, c.transform, gl);
+        }
+    }
+
+    /// Scores, games and the clock of a two-player match.
+    fn scores(&self, c: &Context, gl: &mut GlGraphics) {
+        let game = self.game;
+
+        self.number_renderer.render(
+            game.score[0],
+            WIDTH / 4.0,
+            50.0,
+            100.0,
+            [0.0, 0.0, 0.0],
+            c,
+            gl,
+        );
+
+        self.number_renderer.render(
+            game.score[1],
+            3.0 * WIDTH / 4.0,
+            50.0,
+            100.0,
+            [0.0, 0.0, 0.0],
+            c,
+            gl,
+        );
+
+        let small = BitmapText::new(GREY, 2.0).align(Align::Center);
+
+        if game.config.rules.best_of > 1 {
+            for (i, games) in game.games_won.iter().enumerate() {
+                let x = (1 + 2 * i) as f64 * WIDTH / 4.0;
+                small.draw(&format!("GAMES {games}"), [x, 80.0], c, gl);
+            }
+        }
+
+        if let Some(time_left) = game.time_left() {
+            let clock = if game.overtime {
+                "OVERTIME".to_string()
+            } else {
+                format_clock(time_left)
+            };
+            BitmapText::new(BLACK, 3.0).align(Align::Center).draw(
+                &clock,
+                [WIDTH / 2.0, 40.0],
+                c,
+                gl,
+            );
+        }
+
+        self.rules_line(c, gl);
+    }
+
+    /// Lives left by each player of a four-player match, next to their side,
+    /// and a wall along the side of each player who is out.
+    fn lives(&self, c: &Context, gl: &mut GlGraphics) {
+        let game = self.game;
+        let label = BitmapText::new(GREY, 2.0).align(Align::Center);
+
+        for (i, paddle) in game.players.iter().enumerate() {
+            let player = i + 1;
+            let (position, wall) = match paddle.side {
+                Side::Left => ([100.0, HEIGHT / 2.0 - 60.0], [0.0, 0.0, 4.0, HEIGHT]),
+                Side::Right => (
+                    [WIDTH - 100.0, HEIGHT / 2.0 - 60.0],
+                    [WIDTH - 4.0, 0.0, 4.0, HEIGHT],
+                ),
+                Side::Top => ([WIDTH / 2.0, 20.0], [0.0, 0.0, WIDTH, 4.0]),
+                Side::Bottom => (
+                    [WIDTH / 2.0, HEIGHT - 30.0],
+                    [0.0, HEIGHT - 4.0, WIDTH, 4.0],
+                ),
+            };
+
+            if game.is_out(player) {
+                rectangle(GREY, wall, c.transform, gl);
+                label.draw(&format!("P{player}: OUT"), position, c, gl);
+            } else {
+                let lives = game.lives[i];
+                label.draw(&format!("P{player}: {lives}"), position, c, gl);
+            }
+        }
+    }
+
+    /// Lists the effects on each paddle under its score, and those on the
+    /// ball in the middle, each with a bar that empties as it runs out.
+    fn effect_timers(&self, c: &Context, gl: &mut GlGraphics) {
+        const BAR_WIDTH: f64 = 100.0;
+
+        let definitions = &self.game.config.powerups.definitions;
+        let label = BitmapText::new(GREY, 2.0).align(Align::Center);
+
+        // One column per paddle, in the order they sit from left to right.
+        let columns: &[Affected] = if self.game.config.rules.is_four_player() {
+            &[
+                Affected::Player(1),
+                Affected::Player(3),
+                Affected::Ball,
+                Affected::Player(4),
+                Affected::Player(2),
+            ]
+        } else {
+            &[Affected::Player(1), Affected::Ball, Affected::Player(2)]
+        };
+        let mut rows = vec![0; columns.len()];
+
+        for active in &self.game.effects {
+            let Some(column) = columns
+                .iter()
+                .position(|target| *target == active.effect.target)
+            else {
+                continue;
+            };
+            let x = (column + 1) as f64 * WIDTH / (columns.len() + 1) as f64;
+            let y = 110.0 + 28.0 * rows[column] as f64;
+            rows[column] += 1;
+
+            label.draw(&definitions[active.source].name, [x, y], c, gl);
+
+            let fraction = (active.remaining / active.duration).clamp(0.0, 1.0);
+            rectangle(
+                GREY,
+                [x - BAR_WIDTH / 2.0, y + 17.0, BAR_WIDTH * fraction, 3.0],
+                c.transform,
+                gl,
+            );
+        }
+    }
+
+    fn serve(&self, c: &Context, gl: &mut GlGraphics) {
+        let game = self.game;
+        let ball = &game.balls[0];
+        let [dx, dy] = [ball.angle.cos(), -ball.angle.sin()];
+        let start = ball.radius() + 4.0;
+        let end = ball.radius() + 40.0;
+        line_from_to(
+            GREY,
+            1.5,
+            [ball.position.x + dx * start, ball.position.y + dy * start],
+            [ball.position.x + dx * end, ball.position.y + dy * end],
+            c.transform,
+            gl,
+        );
+
+        let [_, _, serve] = Action::paddle_actions(game.kick_off);
+        let key = self.key(serve);
+        let message = format!("PLAYER {} TO SERVE", game.kick_off);
+        let hint = format!("MOVE TO AIM, {key} TO SERVE");
+        banner(&message, Some(&hint), c, gl);
+    }
+
+    fn point_scored(&self, scorer: usize, game_won: bool, c: &Context, gl: &mut GlGraphics) {
+        let message = if game_won {
+            format!("GAME TO PLAYER {scorer}")
+        } else {
+            format!("POINT TO PLAYER {scorer}")
+        };
+        banner(&message, None, c, gl);
+    }
+
+    fn life_lost(&self, player: usize, eliminated: bool, c: &Context, gl: &mut GlGraphics) {
+        let message = if eliminated {
+            format!("PLAYER {player} IS OUT")
+        } else {
+            format!("PLAYER {player} LOSES A LIFE")
+        };
+        banner(&message, None, c, gl);
+    }
+
+    fn game_over(&self, c: &Context, gl: &mut GlGraphics) {
+        let message = format!("PLAYER {} WINS", self.game.winner);
+        let hint = format!(
+            "{}: REMATCH    {}: QUIT",
+            self.key(Action::Rematch),
+            self.key(Action::Quit)
+        );
+        banner(&message, Some(&hint), c, gl);
+    }
+
+    fn paused(&self, c: &Context, gl: &mut GlGraphics) {
+        rectangle(
+            [1.0, 1.0, 1.0, 0.6],
+            [0.0, 0.0, WIDTH, HEIGHT],
+            c.transform,
+            gl,
+        );
+        let hint = format!(
+            "{}: RESUME    {}: STEP ONE TICK",
+            self.key(Action::Pause),
+            self.key(Action::Step)
+        );
+        banner("PAUSED", Some(&hint), c, gl);
+    }
+
+    /// Lists every action with its key, the selected one in black.
+    fn bindings(&self, menu: &BindingsMenu, c: &Context, gl: &mut GlGraphics) {
+        BitmapText::new(BLACK, 6.0)
+            .align(Align::Center)
+            .draw("KEYS", [WIDTH / 2.0, 60.0], c, gl);
+
+        for (i, action) in Action::ALL.iter().enumerate() {
+            let y = 150.0 + 30.0 * i as f64;
+            let selected = i == menu.selected;
+            let text = BitmapText::new(if selected { BLACK } else { GREY }, 3.0);
+
+            if selected {
+                text.draw(">", [170.0, y], c, gl);
+            }
+            text.draw(action.label(), [200.0, y], c, gl);
+
+            let key = if selected && menu.waiting {
+                "PRESS A KEY".to_string()
+            } else {
+                self.key(*action)
+            };
+            text.draw(&key, [520.0, y], c, gl);
+        }
+
+        let small = BitmapText::new(GREY, 2.0).align(Align::Center);
+        if let Some(notice) = &menu.notice {
+            BitmapText::new(BLACK, 2.0).align(Align::Center).draw(
+                notice,
+                [WIDTH / 2.0, HEIGHT - 110.0],
+                c,
+                gl,
+            );
+        }
+        small.draw(
+            "UP / DOWN: SELECT    ENTER: CHANGE    DELETE: DEFAULTS",
+            [WIDTH / 2.0, HEIGHT - 70.0],
+            c,
+            gl,
+        );
+        let close = format!("{}: SAVE AND CLOSE", key_name(MENU_KEY));
+        small.draw(&close, [WIDTH / 2.0, HEIGHT - 45.0], c, gl);
+    }
+
+    fn key(&self, action: Action) -> String {
+        key_name(self.keys.key(action))
+    }
+
+    fn rules_line(&self, c: &Context, gl: &mut GlGraphics) {
+        BitmapText::new(GREY, 2.0).align(Align::Center).draw(
+            &self.game.config.rules.describe(),
+            [WIDTH / 2.0, HEIGHT - 30.0],
+            c,
+            gl,
+        );
+    }
+}
+
+/// Large centred message with an optional hint underneath.
+fn banner(message: &str, hint: Option<&str>, c: &Context, gl: &mut GlGraphics) {
+    BitmapText::new(BLACK, 5.0).align(Align::Center).draw(
+        message,
+        [WIDTH / 2.0, HEIGHT / 2.0 - 120.0],
+        c,
+        gl,
+    );
+
+    if let Some(hint) = hint {
+        BitmapText::new(GREY, 3.0).align(Align::Center).draw(
+            hint,
+            [WIDTH / 2.0, HEIGHT / 2.0 - 70.0],
+            c,
+            gl,
+        );
+    }
+}
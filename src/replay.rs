@@ -0,0 +1,289 @@
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::config::{Config, ConfigError};
+use crate::input::Inputs;
+use crate::source::{InputSource, TickContext};
+
+/// Bump whenever the file layout or the meaning of recorded inputs changes.
+pub const REPLAY_VERSION: u32 = 1;
+
+/// A recorded match: everything needed to play it back tick for tick.
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    /// Seed the match ran with, whether or not `config` names one.
+    pub seed: u64,
+    pub config: Config,
+    /// Per-tick inputs, run-length encoded.
+    pub inputs: Vec<InputRun>,
+}
+
+/// `inputs` held for `ticks` consecutive ticks.
+#[derive(Serialize, Deserialize)]
+pub struct InputRun {
+    pub ticks: u32,
+    pub inputs: Inputs,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    UnsupportedVersion(u32),
+    Config(ConfigError),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "{err}"),
+            ReplayError::Parse(err) => write!(f, "invalid replay: {err}"),
+            ReplayError::Serialize(err) => write!(f, "could not write replay: {err}"),
+            ReplayError::UnsupportedVersion(version) => write!(
+                f,
+                "replay version {version} is not supported (expected {REPLAY_VERSION})"
+            ),
+            ReplayError::Config(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+impl Replay {
+    pub fn new(seed: u64, config: Config) -> Self {
+        Replay {
+            version: REPLAY_VERSION,
+            seed,
+            config,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        let text = fs::read_to_string(path)?;
+        let mut replay: Replay = ron::from_str(&text).map_err(ReplayError::Parse)?;
+
+        if replay.version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(replay.version));
+        }
+
+        // The recorded settings get the same checks as a config file.
+        replay
+            .config
+            .load_power_ups()
+            .map_err(ReplayError::Config)?;
+        replay.config.validate().map_err(ReplayError::Config)?;
+
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        let config = PrettyConfig::new().depth_limit(2);
+        let text = ron::ser::to_string_pretty(self, config).map_err(ReplayError::Serialize)?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Appends the inputs of one tick.
+    pub fn record(&mut self, inputs: &Inputs) {
+        match self.inputs.last_mut() {
+            Some(run) if run.inputs == *inputs => run.ticks += 1,
+            _ => self.inputs.push(InputRun {
+                ticks: 1,
+                inputs: *inputs,
+            }),
+        }
+    }
+}
+
+/// Feeds a loaded replay back into the simulation one tick at a time.
+pub struct ReplayPlayer {
+    replay: Replay,
+    run: usize,
+    tick_in_run: u32,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        ReplayPlayer {
+            replay,
+            run: 0,
+            tick_in_run: 0,
+        }
+    }
+
+    /// Inputs for the next tick, or `None` once the recording has ended.
+    pub fn next_inputs(&mut self) -> Option<Inputs> {
+        let run = self.replay.inputs.get(self.run)?;
+        let inputs = run.inputs;
+
+        self.tick_in_run += 1;
+        if self.tick_in_run >= run.ticks {
+            self.run += 1;
+            self.tick_in_run = 0;
+        }
+
+        Some(inputs)
+    }
+}
+
+/// Plays one paddle's part of a recorded match, whatever the other paddle
+/// does. The paddle stands still once the recording runs out.
+pub struct ReplaySource {
+    player: usize,
+    playback: ReplayPlayer,
+}
+
+impl ReplaySource {
+    pub fn new(player: usize, replay: Replay) -> Self {
+        ReplaySource {
+            player,
+            playback: ReplayPlayer::new(replay),
+        }
+    }
+}
+
+impl InputSource for ReplaySource {
+    fn update(&mut self, _context: &TickContext, inputs: &mut Inputs) {
+        let recorded = self.playback.next_inputs().unwrap_or_default();
+        *inputs.paddle_mut(self.player) = recorded.paddle(self.player);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{AiController, Difficulty};
+    use crate::constants::MAX_PLAYERS;
+    use crate::game::GameState;
+    use crate::gamepad::Gamepads;
+    use crate::input::{KeyBindings, PaddleInput};
+    use crate::source::update_sources;
+    use std::collections::HashSet;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.load_power_ups().unwrap();
+        config
+    }
+
+    fn paddle(axis: f64) -> Inputs {
+        let mut inputs = Inputs::default();
+        inputs.paddles[0] = PaddleInput { axis, serve: false };
+        inputs
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("pong-{}-{name}.ron", std::process::id()))
+    }
+
+    #[test]
+    fn record_merges_repeated_inputs() {
+        let mut replay = Replay::new(1, config());
+        for inputs in [
+            paddle(1.0),
+            paddle(1.0),
+            paddle(-1.0),
+            paddle(1.0),
+            paddle(1.0),
+        ] {
+            replay.record(&inputs);
+        }
+
+        let ticks: Vec<u32> = replay.inputs.iter().map(|run| run.ticks).collect();
+        assert_eq!(ticks, [2, 1, 2]);
+
+        let mut playback = ReplayPlayer::new(replay);
+        let axes: Vec<f64> = std::iter::from_fn(|| playback.next_inputs())
+            .map(|inputs| inputs.paddles[0].axis)
+            .collect();
+        assert_eq!(axes, [1.0, 1.0, -1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn replay_plays_out_like_the_recorded_match() {
+        let seed = 4;
+        let config = config();
+        let dt = 1.0 / config.tick_rate;
+        let keys = HashSet::new();
+        let bindings = KeyBindings::default();
+        let gamepads = Gamepads::new(config.gamepad.clone(), [false; MAX_PLAYERS]);
+        let mut sources: Vec<Box<dyn InputSource>> = vec![
+            Box::new(AiController::new(1, Difficulty::Easy, seed)),
+            Box::new(AiController::new(2, Difficulty::Easy, seed)),
+        ];
+
+        let mut recorded = GameState::new(seed, config.clone());
+        let mut replay = Replay::new(seed, config.clone());
+        let mut start = true;
+        while !recorded.is_over() && recorded.game_time < 120.0 {
+            let context = TickContext {
+                game: &recorded,
+                dt,
+                keys: &keys,
+                bindings: &bindings,
+                gamepads: &gamepads,
+            };
+            let mut inputs = Inputs {
+                start,
+                ..Inputs::default()
+            };
+            start = false;
+            update_sources(&mut sources, &context, &mut inputs);
+            replay.record(&inputs);
+            recorded.step(dt, &inputs);
+        }
+
+        assert!(recorded.score.iter().sum::<u32>() > 0);
+
+        let path = temp_path("determinism");
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+
+        let mut replayed = GameState::new(loaded.seed, loaded.config.clone());
+        let mut playback = ReplayPlayer::new(loaded);
+        while let Some(inputs) = playback.next_inputs() {
+            replayed.step(dt, &inputs);
+        }
+
+        assert_eq!(replayed.phase, recorded.phase);
+        assert_eq!(replayed.score, recorded.score);
+        assert_eq!(replayed.winner, recorded.winner);
+        assert_eq!(replayed.game_time, recorded.game_time);
+        for (a, b) in replayed.players.iter().zip(&recorded.players) {
+            assert_eq!((a.position.x, a.position.y), (b.position.x, b.position.y));
+        }
+        for (a, b) in replayed.balls.iter().zip(&recorded.balls) {
+            assert_eq!((a.position.x, a.position.y), (b.position.x, b.position.y));
+            assert_eq!(a.angle, b.angle);
+        }
+    }
+
+    #[test]
+    fn load_rejects_an_invalid_config() {
+        let mut config = config();
+        for def in &mut config.powerups.definitions {
+            def.weight = 0.0;
+        }
+
+        let path = temp_path("invalid");
+        Replay::new(1, config).save(&path).unwrap();
+        let loaded = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(loaded, Err(ReplayError::Config(_))));
+    }
+}
@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize};
+
+/// How a game that is level when its time limit runs out gets decided.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Tiebreak {
+    /// The next point wins.
+    GoldenPoint,
+    /// Play on until one player leads by two.
+    LeadByTwo,
+}
+
+impl Tiebreak {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "golden-point" => Some(Tiebreak::GoldenPoint),
+            "lead-by-two" => Some(Tiebreak::LeadByTwo),
+            _ => None,
+        }
+    }
+}
+
+/// How goals count while several balls are in play.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum MultiballScoring {
+    /// The first ball in a goal wins the point and ends the rally.
+    FirstGoal,
+    /// Every ball in a goal scores; the rally goes on until the last ball
+    /// is out or the game is decided.
+    EveryGoal,
+}
+
+impl MultiballScoring {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "first-goal" => Some(MultiballScoring::FirstGoal),
+            "every-goal" => Some(MultiballScoring::EveryGoal),
+            _ => None,
+        }
+    }
+}
+
+/// When a game and the match are over.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MatchRules {
+    /// Points needed to win a game.
+    pub target_score: u32,
+    /// A game at the target score also needs a two point lead (deuce).
+    pub win_by_two: bool,
+    /// Length of a game in seconds. When it runs out the leader wins.
+    pub time_limit: Option<f64>,
+    pub tiebreak: Tiebreak,
+    /// Games in the series; the first to win more than half takes the match.
+    pub best_of: u32,
+    pub multiball: MultiballScoring,
+    /// 2 for the usual match, or 4 with paddles on every side. Four-player
+    /// matches are played for lives instead of points.
+    pub players: usize,
+    /// Goals a player can let in during a four-player match before they are
+    /// out. The last player left wins.
+    pub lives: u32,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        MatchRules {
+            target_score: 10,
+            win_by_two: false,
+            time_limit: None,
+            tiebreak: Tiebreak::GoldenPoint,
+            best_of: 1,
+            multiball: MultiballScoring::FirstGoal,
+            players: 2,
+            lives: 3,
+        }
+    }
+}
+
+impl MatchRules {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.target_score == 0 {
+            return Err("target score must be at least 1".to_string());
+        }
+        if self.best_of == 0 || self.best_of.is_multiple_of(2) {
+            return Err(format!("best of {} must be an odd number", self.best_of));
+        }
+        if let Some(time_limit) = self.time_limit
+            && (time_limit.is_nan() || time_limit <= 0.0)
+        {
+            return Err(format!("time limit {time_limit} must be positive"));
+        }
+        if self.players != 2 && self.players != 4 {
+            return Err(format!("{} players: must be 2 or 4", self.players));
+        }
+        if self.is_four_player() {
+            if self.lives == 0 {
+                return Err("lives must be at least 1".to_string());
+            }
+            if self.time_limit.is_some() || self.best_of > 1 {
+                return Err(
+                    "four-player matches are played for lives, without a time limit or series"
+                        .to_string(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_four_player(&self) -> bool {
+        self.players == 4
+    }
+
+    /// Games a player has to win to take the match.
+    pub fn games_to_win(&self) -> u32 {
+        self.best_of / 2 + 1
+    }
+
+    /// Winner of a game with `score`, if it is decided. `overtime` is set
+    /// once the time limit ran out with the score level.
+    pub fn game_winner(&self, score: [u32; 2], time_up: bool, overtime: bool) -> Option<usize> {
+        let lead = score[0].abs_diff(score[1]);
+        let leader = if score[0] > score[1] { 1 } else { 2 };
+
+        let lead_needed = if self.win_by_two { 2 } else { 1 };
+        if score[0].max(score[1]) >= self.target_score && lead >= lead_needed {
+            return Some(leader);
+        }
+
+        if overtime {
+            let lead_needed = match self.tiebreak {
+                Tiebreak::GoldenPoint => 1,
+                Tiebreak::LeadByTwo => 2,
+            };
+            return (lead >= lead_needed).then_some(leader);
+        }
+
+        (time_up && lead > 0).then_some(leader)
+    }
+
+    /// One-line summary for the screen.
+    pub fn describe(&self) -> String {
+        if self.is_four_player() {
+            let lives = if self.lives == 1 { "LIFE" } else { "LIVES" };
+            return format!("4 PLAYERS  -  {} {lives}", self.lives);
+        }
+
+        let mut parts = vec![format!("FIRST TO {}", self.target_score)];
+
+        if self.win_by_two {
+            parts.push("WIN BY 2".to_string());
+        }
+        if let Some(time_limit) = self.time_limit {
+            let tiebreak = match self.tiebreak {
+                Tiebreak::GoldenPoint => "GOLDEN POINT",
+                Tiebreak::LeadByTwo => "LEAD BY 2",
+            };
+            parts.push(format!("{} LIMIT, {tiebreak}", format_clock(time_limit)));
+        }
+        if self.best_of > 1 {
+            parts.push(format!("BEST OF {}", self.best_of));
+        }
+
+        parts.join("  -  ")
+    }
+}
+
+/// Formats seconds as `m:ss`, rounding up so `0:00` only shows at the end.
+pub fn format_clock(seconds: f64) -> String {
+    let total = seconds.max(0.0).ceil() as u64;
+    format!("{}:{:02}", total / 60, total % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_to_the_target_wins() {
+        let rules = MatchRules::default();
+        assert_eq!(rules.game_winner([9, 8], false, false), None);
+        assert_eq!(rules.game_winner([10, 9], false, false), Some(1));
+        assert_eq!(rules.game_winner([3, 10], false, false), Some(2));
+    }
+
+    #[test]
+    fn win_by_two_plays_on_from_deuce() {
+        let rules = MatchRules {
+            win_by_two: true,
+            ..MatchRules::default()
+        };
+        assert_eq!(rules.game_winner([10, 9], false, false), None);
+        assert_eq!(rules.game_winner([11, 11], false, false), None);
+        assert_eq!(rules.game_winner([11, 13], false, false), Some(2));
+        assert_eq!(rules.game_winner([10, 8], false, false), Some(1));
+    }
+
+    #[test]
+    fn time_up_goes_to_the_leader() {
+        let rules = MatchRules {
+            time_limit: Some(60.0),
+            ..MatchRules::default()
+        };
+        assert_eq!(rules.game_winner([3, 2], false, false), None);
+        assert_eq!(rules.game_winner([3, 2], true, false), Some(1));
+        assert_eq!(rules.game_winner([4, 7], true, false), Some(2));
+        assert_eq!(rules.game_winner([5, 5], true, false), None);
+    }
+
+    #[test]
+    fn overtime_follows_the_tiebreak() {
+        let golden = MatchRules {
+            time_limit: Some(60.0),
+            tiebreak: Tiebreak::GoldenPoint,
+            ..MatchRules::default()
+        };
+        assert_eq!(golden.game_winner([5, 5], true, true), None);
+        assert_eq!(golden.game_winner([5, 6], true, true), Some(2));
+
+        let lead_by_two = MatchRules {
+            tiebreak: Tiebreak::LeadByTwo,
+            ..golden
+        };
+        assert_eq!(lead_by_two.game_winner([6, 5], true, true), None);
+        assert_eq!(lead_by_two.game_winner([7, 6], true, true), None);
+        assert_eq!(lead_by_two.game_winner([8, 6], true, true), Some(1));
+    }
+
+    #[test]
+    fn games_to_win_is_a_majority_of_the_series() {
+        for (best_of, needed) in [(1, 1), (3, 2), (5, 3), (7, 4)] {
+            let rules = MatchRules {
+                best_of,
+                ..MatchRules::default()
+            };
+            assert_eq!(rules.games_to_win(), needed);
+        }
+    }
+}
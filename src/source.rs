@@ -0,0 +1,190 @@
+use piston::{Event, Key};
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use crate::ai::{AiController, Difficulty};
+use crate::config::Config;
+use crate::game::GameState;
+use crate::gamepad::Gamepads;
+use crate::input::{Inputs, KeyBindings, PaddleInput};
+use crate::mouse::{MouseControl, MouseMode};
+use crate::net::PeerSource;
+use crate::replay::{Replay, ReplaySource};
+
+/// Everything a source can look at when deciding a tick's input.
+pub struct TickContext<'a> {
+    pub game: &'a GameState,
+    pub dt: f64,
+    pub keys: &'a HashSet<Key>,
+    pub bindings: &'a KeyBindings,
+    pub gamepads: &'a Gamepads,
+}
+
+/// Decides one paddle's input every tick: a person at the keyboard, the AI,
+/// a recording or a player on another machine.
+pub trait InputSource {
+    /// Writes this source's paddle input for the next tick into `inputs`.
+    /// Remote sources also see, and add to, what the local ones decided.
+    fn update(&mut self, context: &TickContext, inputs: &mut Inputs);
+
+    /// Handles a window event, for sources that read a device of their own.
+    fn event(&mut self, _event: &Event) {}
+
+    /// Whether `update` needs every local source's input for the tick, so
+    /// it has to run last.
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    /// Set once the source can't go on, which ends the game.
+    fn disconnected(&self) -> bool {
+        false
+    }
+}
+
+/// Runs every source for one tick, the remote ones last.
+pub fn update_sources(
+    sources: &mut [Box<dyn InputSource>],
+    context: &TickContext,
+    inputs: &mut Inputs,
+) {
+    for remote in [false, true] {
+        for source in sources
+            .iter_mut()
+            .filter(|source| source.is_remote() == remote)
+        {
+            source.update(context, inputs);
+        }
+    }
+}
+
+/// A source as given on the command line with `--p1` to `--p4`.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub enum SourceSpec {
+    /// The keyboard and any controller that joins.
+    #[default]
+    Local,
+    Keyboard,
+    Gamepad,
+    Mouse(MouseMode),
+    Ai(Difficulty),
+    /// The paddle's inputs from a recorded match.
+    Replay(PathBuf),
+    /// Waits on this address for the other machine to connect.
+    Listen(String),
+    /// Connects to a machine listening on this address.
+    Connect(String),
+}
+
+impl SourceSpec {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (kind, argument) = match value.split_once(':') {
+            Some((kind, argument)) => (kind, Some(argument)),
+            None => (value, None),
+        };
+
+        let spec = match (kind, argument) {
+            ("local", None) => SourceSpec::Local,
+            ("keyboard", None) => SourceSpec::Keyboard,
+            ("gamepad", None) => SourceSpec::Gamepad,
+            ("mouse", None) => SourceSpec::Mouse(MouseMode::Absolute),
+            ("mouse", Some(mode)) => SourceSpec::Mouse(
+                MouseMode::parse(mode).ok_or_else(|| format!("unknown mouse mode '{mode}'"))?,
+            ),
+            ("ai", Some(level)) => SourceSpec::Ai(
+                Difficulty::parse(level)
+                    .ok_or_else(|| format!("unknown AI difficulty '{level}'"))?,
+            ),
+            ("replay", Some(path)) => SourceSpec::Replay(path.into()),
+            ("listen", Some(address)) => SourceSpec::Listen(address.to_string()),
+            ("connect", Some(address)) => SourceSpec::Connect(address.to_string()),
+            _ => return Err(format!("unknown input source '{value}'")),
+        };
+        Ok(spec)
+    }
+
+    /// Creates the source for `player`. `local` is this machine's own
+    /// config, for the devices attached to it. A peer has to be connected
+    /// beforehand, as it decides the seed and config, and is passed in
+    /// `peer`.
+    pub fn build(
+        &self,
+        player: usize,
+        seed: u64,
+        local: &Config,
+        peer: &mut Option<PeerSource>,
+    ) -> Result<Box<dyn InputSource>, String> {
+        let source: Box<dyn InputSource> = match self {
+            SourceSpec::Local => Box::new(LocalSource::new(player, true, true)),
+            SourceSpec::Keyboard => Box::new(LocalSource::new(player, true, false)),
+            SourceSpec::Gamepad => Box::new(LocalSource::new(player, false, true)),
+            SourceSpec::Mouse(mode) => Box::new(MouseControl::new(player, *mode, &local.mouse)),
+            SourceSpec::Ai(difficulty) => Box::new(AiController::new(player, *difficulty, seed)),
+            SourceSpec::Replay(path) => {
+                let replay =
+                    Replay::load(path).map_err(|err| format!("{}: {err}", path.display()))?;
+                Box::new(ReplaySource::new(player, replay))
+            }
+            SourceSpec::Listen(_) | SourceSpec::Connect(_) => Box::new(
+                peer.take()
+                    .ok_or_else(|| format!("player {player} isn't connected"))?,
+            ),
+        };
+        Ok(source)
+    }
+
+    /// Whether a controller may join the player using this source.
+    pub fn takes_gamepad(&self) -> bool {
+        matches!(self, SourceSpec::Local | SourceSpec::Gamepad)
+    }
+
+    /// Whether the source can play without a window, so `--headless` can
+    /// use it.
+    pub fn is_headless(&self) -> bool {
+        matches!(
+            self,
+            SourceSpec::Ai(_)
+                | SourceSpec::Replay(_)
+                | SourceSpec::Listen(_)
+                | SourceSpec::Connect(_)
+        )
+    }
+
+    pub fn is_peer(&self) -> bool {
+        matches!(self, SourceSpec::Listen(_) | SourceSpec::Connect(_))
+    }
+}
+
+/// A person at this machine, on the keyboard, a controller or both.
+pub struct LocalSource {
+    player: usize,
+    keyboard: bool,
+    gamepad: bool,
+}
+
+impl LocalSource {
+    pub fn new(player: usize, keyboard: bool, gamepad: bool) -> Self {
+        LocalSource {
+            player,
+            keyboard,
+            gamepad,
+        }
+    }
+}
+
+impl InputSource for LocalSource {
+    fn update(&mut self, context: &TickContext, inputs: &mut Inputs) {
+        let mut paddle = PaddleInput::default();
+
+        if self.keyboard {
+            let keys = Inputs::from_keys(context.keys, context.bindings);
+            paddle.add(keys.paddle(self.player));
+        }
+        if self.gamepad {
+            let vertical = context.game.player(self.player).side.is_vertical();
+            paddle.add(context.gamepads.paddle_input(self.player, vertical));
+        }
+
+        *inputs.paddle_mut(self.player) = paddle;
+    }
+}
@@ -0,0 +1,32 @@
+/// A size or speed that effects can push up or down, within fixed limits.
+///
+/// Changes are summed in `modifier` unclamped, so undoing a change always
+/// gets back to where it started; only the effective value is clamped.
+#[derive(Clone, Copy, Debug)]
+pub struct Stat {
+    /// Value without any effects.
+    pub base: f64,
+    modifier: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Stat {
+    pub fn new(base: f64, min: f64, max: f64) -> Self {
+        Stat {
+            base,
+            modifier: 0.0,
+            min,
+            max,
+        }
+    }
+
+    /// Value with every effect applied, kept within the limits.
+    pub fn value(&self) -> f64 {
+        (self.base + self.modifier).clamp(self.min, self.max)
+    }
+
+    pub fn modify(&mut self, by: f64) {
+        self.modifier += by;
+    }
+}
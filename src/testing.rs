@@ -0,0 +1,10 @@
+//! Helpers shared by the unit tests.
+
+/// Fails unless `actual` is `expected` give or take rounding errors.
+#[track_caller]
+pub fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-9,
+        "expected {expected}, got {actual}"
+    );
+}
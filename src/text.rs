@@ -0,0 +1,161 @@
+use graphics::{Context, Transformed, rectangle};
+use opengl_graphics::GlGraphics;
+
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+
+/// 5x7 bitmap glyphs; each row's low five bits are its pixels, left to right.
+const GLYPHS: &[(char, [u8; GLYPH_HEIGHT])] = &[
+    ('A', [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+    ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+    ('D', [0x1E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1E]),
+    ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+    ('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]),
+    ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('I', [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
+    ('M', [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+    ('Q', [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D]),
+    ('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+    ('S', [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]),
+    ('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]),
+    ('X', [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x0A, 0x04, 0x04, 0x04, 0x04]),
+    ('Z', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    (' ', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+    (':', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00]),
+    ('-', [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00]),
+    ('+', [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00]),
+    ('=', [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08]),
+    ('\'', [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00]),
+    ('!', [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04]),
+    ('?', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+    ('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+    ('(', [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+    (')', [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]),
+    ('<', [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02]),
+    ('>', [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08]),
+];
+
+#[derive(Clone, Copy)]
+pub enum Align {
+    Left,
+    Center,
+}
+
+/// Draws text in a blocky bitmap font, one rectangle per run of pixels.
+///
+/// ```ignore
+/// BitmapText::new(BLACK, 3.0)
+///     .align(Align::Center)
+///     .draw("PAUSED", [WIDTH / 2.0, 300.0], &c, gl);
+/// ```
+#[derive(Clone, Copy)]
+pub struct BitmapText {
+    color: [f32; 4],
+    /// Size of one font pixel on screen.
+    pixel: f64,
+    align: Align,
+}
+
+impl BitmapText {
+    pub fn new(color: [f32; 4], pixel: f64) -> Self {
+        BitmapText {
+            color,
+            pixel,
+            align: Align::Left,
+        }
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn width(&self, text: &str) -> f64 {
+        let chars = text.chars().count();
+        if chars == 0 {
+            return 0.0;
+        }
+        ((GLYPH_WIDTH + 1) * chars - 1) as f64 * self.pixel
+    }
+
+    /// Draws `text` with its top edge at `position[1]`; `position[0]` is the
+    /// left edge or the centre depending on the alignment.
+    pub fn draw(&self, text: &str, position: [f64; 2], c: &Context, gl: &mut GlGraphics) {
+        let x = match self.align {
+            Align::Left => position[0],
+            Align::Center => position[0] - self.width(text) / 2.0,
+        };
+        let transform = c.transform.trans(x, position[1]);
+
+        for (i, ch) in text.chars().enumerate() {
+            let rows = glyph(ch);
+            let left = (i * (GLYPH_WIDTH + 1)) as f64;
+
+            for (row, bits) in rows.iter().enumerate() {
+                let mut column = 0;
+                while column < GLYPH_WIDTH {
+                    if !is_set(*bits, column) {
+                        column += 1;
+                        continue;
+                    }
+
+                    let start = column;
+                    while column < GLYPH_WIDTH && is_set(*bits, column) {
+                        column += 1;
+                    }
+
+                    rectangle(
+                        self.color,
+                        [
+                            (left + start as f64) * self.pixel,
+                            row as f64 * self.pixel,
+                            (column - start) as f64 * self.pixel,
+                            self.pixel,
+                        ],
+                        transform,
+                        gl,
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn glyph(ch: char) -> &'static [u8; GLYPH_HEIGHT] {
+    let ch = ch.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .find(|(glyph, _)| *glyph == ch)
+        .or_else(|| GLYPHS.iter().find(|(glyph, _)| *glyph == '?'))
+        .map(|(_, rows)| rows)
+        .unwrap()
+}
+
+fn is_set(bits: u8, column: usize) -> bool {
+    bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0
+}
@@ -0,0 +1,36 @@
+/// Longest frame the accumulator will try to catch up on, so a stall
+/// (window drag, breakpoint) doesn't turn into a burst of hundreds of ticks.
+const MAX_FRAME_TIME: f64 = 0.25;
+
+/// Turns variable frame times into a whole number of fixed-length ticks.
+pub struct FixedTimestep {
+    tick: f64,
+    accumulator: f64,
+}
+
+impl FixedTimestep {
+    pub fn new(tick_rate: f64) -> Self {
+        FixedTimestep {
+            tick: 1.0 / tick_rate,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Length of one tick in seconds.
+    pub fn tick(&self) -> f64 {
+        self.tick
+    }
+
+    /// Adds `dt` seconds of elapsed time and returns how many ticks are due.
+    pub fn advance(&mut self, dt: f64) -> u32 {
+        self.accumulator += dt.min(MAX_FRAME_TIME);
+
+        let mut ticks = 0;
+        while self.accumulator >= self.tick {
+            self.accumulator -= self.tick;
+            ticks += 1;
+        }
+
+        ticks
+    }
+}